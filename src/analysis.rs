@@ -0,0 +1,220 @@
+use std::{collections::HashMap, mem};
+
+use crate::{
+    expression::Expr,
+    interpreter::Interpreter,
+    lox_error::SyntaxError,
+    parse,
+    resolver::Resolver,
+    statement::{ClassStmt, FunctionDecl, ImportStmt},
+    take_syntax_errors,
+    token::{Span, Token},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+    Method,
+    Import,
+}
+
+// A name declared in the source, with everywhere it is referred to.
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub global: bool,
+    pub declaration: Span,
+    pub references: Vec<Span>,
+    // How it was declared, like `fun add(a, b)` or `class Dog < Animal`.
+    pub detail: String,
+    // The class of a method, the function of a parameter.
+    pub parent: Option<usize>,
+    // The lines from the declaration to the end of the enclosing scope.
+    pub visible: (u32, u32),
+}
+
+// What the resolver declares a name with, for the symbol's kind and detail.
+pub(crate) enum Declaration<'a> {
+    Variable,
+    CatchVariable,
+    Parameter,
+    Function(&'a FunctionDecl),
+    Class(&'a ClassStmt),
+    Method(&'a ClassStmt, &'a FunctionDecl),
+    Import(&'a ImportStmt),
+}
+
+// What the names in a source refer to, as found by the resolver. Methods and fields aren't
+// lexically scoped, their uses are listed by name in `properties`.
+#[derive(Default, Debug)]
+pub struct Analysis {
+    pub symbols: Vec<Symbol>,
+    // Names after a `.`, like `name` in `this.name`.
+    pub properties: Vec<Token>,
+    // Variables which aren't declared in the source, like the builtins.
+    pub unresolved: Vec<Token>,
+    // The resolver doesn't track globals, they are bound by name here.
+    globals: HashMap<String, usize>,
+    // The last line seen, which ends the scopes closed on it.
+    last_line: u32,
+}
+
+impl Analysis {
+    // Parses and resolves the source, resolution errors are left to `compile` to report.
+    pub fn new(source: &str) -> Result<Self, Vec<SyntaxError>> {
+        let statements = parse(source)?;
+
+        let mut interpreter = Interpreter::new();
+        let mut resolver = Resolver::with_analysis(&mut interpreter);
+        resolver.resolve(&statements);
+        let analysis = resolver.into_analysis();
+        let _ = take_syntax_errors();
+        Ok(analysis)
+    }
+
+    // The symbol declared or referred to at the position.
+    pub fn symbol_at(&self, line: u32, column: u32) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| {
+            symbol.declaration.contains(line, column)
+                || symbol
+                    .references
+                    .iter()
+                    .any(|reference| reference.contains(line, column))
+        })
+    }
+
+    // The symbols which can be referred to by name on the line, innermost first. Globals
+    // count everywhere, functions can use the ones declared after them.
+    pub fn visible_at(&self, line: u32) -> Vec<&Symbol> {
+        let mut visible: Vec<&Symbol> = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind != SymbolKind::Method)
+            .filter(|symbol| {
+                symbol.global || (symbol.visible.0 <= line && line <= symbol.visible.1)
+            })
+            .collect();
+        visible.reverse();
+        visible
+    }
+
+    pub(crate) fn see_line(&mut self, line: u32) {
+        self.last_line = self.last_line.max(line);
+    }
+
+    pub(crate) fn declare(
+        &mut self,
+        name: &Token,
+        declaration: Declaration,
+        global: bool,
+        parent: Option<usize>,
+    ) -> usize {
+        self.see_line(name.line);
+        let (kind, detail) = match declaration {
+            Declaration::Variable => (SymbolKind::Variable, format!("var {}", name.lexeme)),
+            Declaration::CatchVariable => {
+                (SymbolKind::Variable, format!("catch ({})", name.lexeme))
+            }
+            Declaration::Parameter => (SymbolKind::Parameter, name.lexeme.to_string()),
+            Declaration::Function(function) => (
+                SymbolKind::Function,
+                format!("fun {}({})", name.lexeme, parameters(function)),
+            ),
+            Declaration::Class(class) => match &class.superclass {
+                Some(Expr::Variable(superclass)) => (
+                    SymbolKind::Class,
+                    format!("class {} < {}", name.lexeme, superclass.name.lexeme),
+                ),
+                _ => (SymbolKind::Class, format!("class {}", name.lexeme)),
+            },
+            Declaration::Method(class, method) => (
+                SymbolKind::Method,
+                format!(
+                    "fun {}.{}({})",
+                    class.name.lexeme,
+                    name.lexeme,
+                    parameters(method)
+                ),
+            ),
+            Declaration::Import(import) => {
+                (SymbolKind::Import, format!("import {}", import.path.lexeme))
+            }
+        };
+
+        self.symbols.push(Symbol {
+            name: name.lexeme.to_string(),
+            kind,
+            global,
+            declaration: name.span(),
+            references: Vec::new(),
+            detail,
+            parent,
+            visible: (name.line, name.line),
+        });
+        let symbol = self.symbols.len() - 1;
+        if global {
+            self.globals.insert(name.lexeme.to_string(), symbol);
+        }
+        symbol
+    }
+
+    pub(crate) fn end_scope(&mut self, symbols: impl Iterator<Item = usize>) {
+        for symbol in symbols {
+            self.symbols[symbol].visible.1 = self.last_line;
+        }
+    }
+
+    // A variable resolved to the symbol, or to a global when there is none.
+    pub(crate) fn reference(&mut self, name: &Token, symbol: Option<usize>) {
+        self.see_line(name.line);
+        let symbol = symbol.or_else(|| self.globals.get(name.lexeme.as_ref()).copied());
+        match symbol {
+            Some(symbol) => self.symbols[symbol].references.push(name.span()),
+            None => self.unresolved.push(name.clone()),
+        }
+    }
+
+    pub(crate) fn property(&mut self, name: &Token) {
+        self.see_line(name.line);
+        self.properties.push(name.clone());
+    }
+
+    // Binds the globals functions referred to before they were declared.
+    pub(crate) fn finish(mut self) -> Self {
+        for name in mem::take(&mut self.unresolved) {
+            match self.globals.get(name.lexeme.as_ref()) {
+                Some(&symbol) => self.symbols[symbol].references.push(name.span()),
+                None => self.unresolved.push(name),
+            }
+        }
+        let globals: Vec<usize> = self.globals.values().copied().collect();
+        self.last_line = u32::MAX;
+        self.end_scope(globals.into_iter());
+
+        // Methods are looked up by name when called, any property with the name may be one.
+        for symbol in &mut self.symbols {
+            if symbol.kind == SymbolKind::Method {
+                symbol.references = self
+                    .properties
+                    .iter()
+                    .filter(|property| *property.lexeme == symbol.name)
+                    .map(Token::span)
+                    .collect();
+            }
+        }
+        self
+    }
+}
+
+fn parameters(function: &FunctionDecl) -> String {
+    let params: Vec<&str> = function
+        .params
+        .iter()
+        .map(|param| param.lexeme.as_ref())
+        .collect();
+    params.join(", ")
+}
@@ -0,0 +1,202 @@
+use crate::{
+    expression::Expr,
+    statement::{FunctionDecl, Stmt},
+};
+
+// Prints expressions in a lisp like form, eg: `-123 * (45.67)` is `(* (- 123) (group 45.67))`.
+// Statements get one line each, with the statements they contain indented below them.
+pub struct AstPrinter;
+
+impl AstPrinter {
+    pub fn print_program(&self, statements: &[Stmt]) -> String {
+        let mut output = String::new();
+        for statement in statements {
+            self.statement(statement, 0, &mut output);
+        }
+        output
+    }
+
+    fn statement(&self, stmt: &Stmt, depth: usize, output: &mut String) {
+        let line = match stmt {
+            Stmt::Block(block) => {
+                return self.nested("(block)", &block.statements, depth, output);
+            }
+            Stmt::Break(_) => String::from("(break)"),
+            Stmt::Class(class) => {
+                let header = match &class.superclass {
+                    Some(superclass) => {
+                        format!("(class {} < {})", class.name.lexeme, self.print(superclass))
+                    }
+                    None => format!("(class {})", class.name.lexeme),
+                };
+                self.line(&header, depth, output);
+                for method in &class.methods {
+                    self.function(method, depth + 1, output);
+                }
+                return;
+            }
+            Stmt::Export(declaration) => {
+                self.line("(export)", depth, output);
+                return self.statement(declaration, depth + 1, output);
+            }
+            Stmt::Expression(expr) => format!("(; {})", self.print(expr)),
+            Stmt::ForIn(for_in) => {
+                let header = format!(
+                    "(for-in {} {})",
+                    for_in.name.lexeme,
+                    self.print(&for_in.iterable)
+                );
+                self.line(&header, depth, output);
+                return self.statement(&for_in.body, depth + 1, output);
+            }
+            Stmt::Function(function) => return self.function(function, depth, output),
+            Stmt::If(if_stmt) => {
+                self.line(
+                    &format!("(if {})", self.print(&if_stmt.condition)),
+                    depth,
+                    output,
+                );
+                self.statement(&if_stmt.then_branch, depth + 1, output);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.line("(else)", depth, output);
+                    self.statement(else_branch, depth + 1, output);
+                }
+                return;
+            }
+            Stmt::Import(import) => match &import.alias {
+                Some(alias) => format!("(import {} as {})", import.path.lexeme, alias.lexeme),
+                None => {
+                    let names: Vec<&str> = import
+                        .names
+                        .iter()
+                        .map(|name| name.lexeme.as_ref())
+                        .collect();
+                    format!("(import ({}) from {})", names.join(" "), import.path.lexeme)
+                }
+            },
+            Stmt::Print(print) => self.parenthesize("print", &[&print.value]),
+            Stmt::Return(return_stmt) => match &return_stmt.value {
+                Some(value) => self.parenthesize("return", &[value]),
+                None => String::from("(return)"),
+            },
+            Stmt::Throw(throw) => self.parenthesize("throw", &[&throw.value]),
+            Stmt::Try(try_stmt) => {
+                self.nested("(try)", &try_stmt.body, depth, output);
+                if let Some(catch) = &try_stmt.catch {
+                    let header = format!("(catch {})", catch.name.lexeme);
+                    self.nested(&header, &catch.body, depth, output);
+                }
+                if let Some(finally) = &try_stmt.finally {
+                    self.nested("(finally)", finally, depth, output);
+                }
+                return;
+            }
+            Stmt::Var(var_stmt) => match &var_stmt.initializer {
+                Some(initializer) => {
+                    self.parenthesize(&format!("var {}", var_stmt.name.lexeme), &[initializer])
+                }
+                None => format!("(var {})", var_stmt.name.lexeme),
+            },
+            Stmt::While(while_stmt) => {
+                let header = format!("(while {})", self.print(&while_stmt.condition));
+                self.line(&header, depth, output);
+                return self.statement(&while_stmt.body, depth + 1, output);
+            }
+        };
+        self.line(&line, depth, output);
+    }
+
+    fn function(&self, function: &FunctionDecl, depth: usize, output: &mut String) {
+        let name = function
+            .name
+            .as_ref()
+            .map_or("", |name| name.lexeme.as_ref());
+        let params: Vec<&str> = function
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_ref())
+            .collect();
+        let header = format!("(fun {name} ({}))", params.join(" "));
+        self.nested(&header, &function.body, depth, output);
+    }
+
+    fn nested(&self, header: &str, statements: &[Stmt], depth: usize, output: &mut String) {
+        self.line(header, depth, output);
+        for statement in statements {
+            self.statement(statement, depth + 1, output);
+        }
+    }
+
+    fn line(&self, line: &str, depth: usize, output: &mut String) {
+        output.push_str(&"  ".repeat(depth));
+        output.push_str(line);
+        output.push('\n');
+    }
+
+    pub fn print(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Assign(assign) => {
+                self.parenthesize(&format!("= {}", assign.name.lexeme), &[&assign.value])
+            }
+            Expr::Binary(binary) => {
+                self.parenthesize(&binary.operator.lexeme, &[&binary.left, &binary.right])
+            }
+            Expr::Call(call) => {
+                let mut exprs = vec![call.callee.as_ref()];
+                exprs.extend(call.arguments.iter());
+                self.parenthesize("call", &exprs)
+            }
+            Expr::Get(get) => self.parenthesize(&format!(". {}", get.name.lexeme), &[&get.object]),
+            Expr::Grouping(expr) => self.parenthesize("group", &[expr]),
+            Expr::Index(index) => self.parenthesize("[]", &[&index.object, &index.index]),
+            Expr::Lambda(function) => self.lambda(function),
+            Expr::List(list) => {
+                let elements: Vec<&Expr> = list.elements.iter().collect();
+                self.parenthesize("list", &elements)
+            }
+            Expr::Literal(literal) => literal.value.to_string(),
+            Expr::Logical(logical) => {
+                self.parenthesize(&logical.operator.lexeme, &[&logical.left, &logical.right])
+            }
+            Expr::Map(map) => {
+                let entries: Vec<String> = map
+                    .entries
+                    .iter()
+                    .map(|(key, value)| format!("({} {})", self.print(key), self.print(value)))
+                    .collect();
+                format!("(map {})", entries.join(" "))
+            }
+            Expr::Set(set) => self.parenthesize(
+                &format!("= {}", set.name.lexeme),
+                &[&set.object, &set.value],
+            ),
+            Expr::SetIndex(set_index) => self.parenthesize(
+                "[]=",
+                &[&set_index.object, &set_index.index, &set_index.value],
+            ),
+            Expr::Super(super_expr) => format!("(super {})", super_expr.method.lexeme),
+            Expr::This(_) => String::from("this"),
+            Expr::Unary(unary) => self.parenthesize(&unary.operator.lexeme, &[&unary.right]),
+            Expr::Variable(variable) => variable.name.lexeme.to_string(),
+        }
+    }
+
+    fn lambda(&self, function: &FunctionDecl) -> String {
+        let params: Vec<&str> = function
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_ref())
+            .collect();
+        format!("(fun ({}) ...)", params.join(" "))
+    }
+
+    fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> String {
+        let mut output = format!("({name}");
+        for expr in exprs {
+            output.push(' ');
+            output.push_str(&self.print(expr));
+        }
+        output.push(')');
+        output
+    }
+}
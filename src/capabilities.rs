@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+
+use crate::{runtime_error::RuntimeError, token::Token};
+
+// Native operations which reach outside the interpreter. Scripts can only use the ones
+// the embedder allowed. File access is checked for the path at hand.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Capability<'a> {
+    FsRead(&'a Path),
+    FsWrite(&'a Path),
+    Env,
+    Exit,
+    Clock,
+    Stdin,
+}
+
+impl Capability<'_> {
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::FsRead(_) => "fs.read",
+            Capability::FsWrite(_) => "fs.write",
+            Capability::Env => "env",
+            Capability::Exit => "exit",
+            Capability::Clock => "clock",
+            Capability::Stdin => "stdin",
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+enum PathAccess {
+    // Only paths under one of the roots.
+    Roots(Vec<PathBuf>),
+    #[default]
+    Denied,
+    Any,
+}
+
+// What a script may do. The default allows nothing, untrusted code can only compute and print.
+#[derive(Clone, Default)]
+pub struct Capabilities {
+    read: PathAccess,
+    write: PathAccess,
+    env: bool,
+    exit: bool,
+    clock: bool,
+    stdin: bool,
+}
+
+impl Capabilities {
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    // Full access, used by the command line where the user runs their own scripts.
+    pub fn all() -> Self {
+        Self {
+            read: PathAccess::Any,
+            write: PathAccess::Any,
+            env: true,
+            exit: true,
+            clock: true,
+            stdin: true,
+        }
+    }
+
+    // Allows reading files and importing modules under `root`.
+    pub fn allow_read(mut self, root: impl AsRef<Path>) -> Self {
+        add_root(&mut self.read, root.as_ref());
+        self
+    }
+
+    pub fn allow_write(mut self, root: impl AsRef<Path>) -> Self {
+        add_root(&mut self.write, root.as_ref());
+        self
+    }
+
+    pub fn allow_env(mut self) -> Self {
+        self.env = true;
+        self
+    }
+
+    pub fn allow_exit(mut self) -> Self {
+        self.exit = true;
+        self
+    }
+
+    pub fn allow_clock(mut self) -> Self {
+        self.clock = true;
+        self
+    }
+
+    pub fn allow_stdin(mut self) -> Self {
+        self.stdin = true;
+        self
+    }
+
+    pub fn check(&self, token: &Token, capability: Capability) -> Result<(), RuntimeError> {
+        let allowed = match capability {
+            Capability::FsRead(path) => path_allowed(&self.read, path),
+            Capability::FsWrite(path) => path_allowed(&self.write, path),
+            Capability::Env => self.env,
+            Capability::Exit => self.exit,
+            Capability::Clock => self.clock,
+            Capability::Stdin => self.stdin,
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(denied(token, capability))
+        }
+    }
+}
+
+fn add_root(access: &mut PathAccess, root: &Path) {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    match access {
+        PathAccess::Roots(roots) => roots.push(root),
+        PathAccess::Denied => *access = PathAccess::Roots(vec![root]),
+        PathAccess::Any => (),
+    }
+}
+
+fn path_allowed(access: &PathAccess, path: &Path) -> bool {
+    match access {
+        PathAccess::Any => true,
+        PathAccess::Denied => false,
+        // Symlinks and `..` are resolved first, so they can't be used to escape a root.
+        PathAccess::Roots(roots) => {
+            absolute_path(path).is_some_and(|path| roots.iter().any(|root| path.starts_with(root)))
+        }
+    }
+}
+
+// Files which don't exist yet are resolved through their parent directory.
+fn absolute_path(path: &Path) -> Option<PathBuf> {
+    if let Ok(path) = path.canonicalize() {
+        return Some(path);
+    }
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name()?;
+    Some(parent.canonicalize().ok()?.join(file_name))
+}
+
+fn denied(token: &Token, capability: Capability) -> RuntimeError {
+    let message = match capability {
+        Capability::FsRead(path) | Capability::FsWrite(path) => format!(
+            "Capability '{}' denied for '{}'.",
+            capability.name(),
+            path.display()
+        ),
+        _ => format!("Capability '{}' denied.", capability.name()),
+    };
+    RuntimeError::new(token, message)
+}
@@ -4,13 +4,43 @@ use crate::lox_error;
 use crate::token::{Literal, Token};
 use crate::token_type::TokenType;
 
+pub const KEYWORDS: [(&str, TokenType); 24] = [
+    ("and", TokenType::And),
+    ("break", TokenType::Break),
+    ("catch", TokenType::Catch),
+    ("class", TokenType::Class),
+    ("else", TokenType::Else),
+    ("export", TokenType::Export),
+    ("false", TokenType::False),
+    ("finally", TokenType::Finally),
+    ("for", TokenType::For),
+    ("fun", TokenType::Fun),
+    ("if", TokenType::If),
+    ("import", TokenType::Import),
+    ("in", TokenType::In),
+    ("nil", TokenType::Nil),
+    ("or", TokenType::Or),
+    ("print", TokenType::Print),
+    ("return", TokenType::Return),
+    ("super", TokenType::Super),
+    ("this", TokenType::This),
+    ("throw", TokenType::Throw),
+    ("true", TokenType::True),
+    ("try", TokenType::Try),
+    ("var", TokenType::Var),
+    ("while", TokenType::While),
+];
+
 pub struct Scanner<'a> {
     source: &'a str,
     source_iter: std::str::CharIndices<'a>,
-    tokens: Vec<Token<'a>>,
+    tokens: Vec<Token>,
     start: usize,   // keep track of idx of start byte of lexeme
     current: usize, // keep track of idx of current iter byte of lexeme
     line: u32,
+    line_start: usize,       // idx of the first byte of the current line
+    token_line_start: usize, // idx of the first byte of the line the current lexeme starts on
+    keep_comments: bool,
 }
 
 impl<'a> Scanner<'a> {
@@ -21,7 +51,18 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            token_line_start: 0,
             source_iter: source.char_indices(),
+            keep_comments: false,
+        }
+    }
+
+    // Also produces a Comment token for every comment, for tools like the formatter.
+    pub fn with_comments(source: &'a str) -> Self {
+        Scanner {
+            keep_comments: true,
+            ..Scanner::new(source)
         }
     }
 
@@ -29,6 +70,7 @@ impl<'a> Scanner<'a> {
         while !self.is_at_end() {
             // we are at the beginning of the next lexeme.
             self.start = self.current;
+            self.token_line_start = self.line_start;
             self.scan_token();
         }
 
@@ -50,6 +92,9 @@ impl<'a> Scanner<'a> {
                 ')' => self.add_token(TokenType::RightParen),
                 '{' => self.add_token(TokenType::LeftBrace),
                 '}' => self.add_token(TokenType::RightBrace),
+                '[' => self.add_token(TokenType::LeftBracket),
+                ']' => self.add_token(TokenType::RightBracket),
+                ':' => self.add_token(TokenType::Colon),
                 ',' => self.add_token(TokenType::Comma),
                 '.' => self.add_token(TokenType::Dot),
                 '-' => self.add_token(TokenType::Minus),
@@ -67,6 +112,8 @@ impl<'a> Scanner<'a> {
                 '=' => {
                     let c_type = if self.match_char('=') {
                         TokenType::EqualEqual
+                    } else if self.match_char('>') {
+                        TokenType::Arrow
                     } else {
                         TokenType::Equal
                     };
@@ -98,16 +145,17 @@ impl<'a> Scanner<'a> {
                             }
                             self.advance();
                         }
+                        self.add_comment();
                     } else if self.match_char('*') {
                         // We have found a block comment, so we keep consuming the whole block comment
-                        while let Some((_, ch)) = self.peek() {
+                        while let Some((idx, ch)) = self.peek() {
                             if ch == '\n' {
-                                self.line += 1;
+                                self.new_line(idx + 1);
                             }
 
                             // We have found the end of the comment
                             if ch == '*'
-                                && self.peek_next().map_or(false, |(_, next_ch)| {
+                                && self.peek_next().is_some_and(|(_, next_ch)| {
                                     self.advance(); // consume the *
                                     next_ch == '/'
                                 })
@@ -126,6 +174,7 @@ impl<'a> Scanner<'a> {
                         } else {
                             // Consume the last /
                             self.advance();
+                            self.add_comment();
                         }
                     } else {
                         self.add_token(TokenType::Slash);
@@ -134,7 +183,7 @@ impl<'a> Scanner<'a> {
                 // Consume and ignore these white space chars
                 ' ' | '\r' | '\t' => (),
                 // Consume and ignore new line char and just move the line by 1
-                '\n' => self.line += 1,
+                '\n' => self.new_line(self.current),
                 '"' => self.string(),
                 // If we find a digit, we consume the whole number
                 '0'..='9' => self.number(),
@@ -149,16 +198,32 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    fn add_comment(&mut self) {
+        if self.keep_comments {
+            self.add_token(TokenType::Comment);
+        }
+    }
+
     fn add_token(&mut self, c_type: TokenType) {
         self.add_token_with_literal(c_type, None);
     }
 
-    fn add_token_with_literal(&mut self, c_type: TokenType, literal: Option<Literal<'a>>) {
+    fn add_token_with_literal(&mut self, c_type: TokenType, literal: Option<Literal>) {
         let text = &self.source[self.start..self.current];
-        let new_token = Token::new(c_type, text, literal, self.line);
+        let mut new_token = Token::new(c_type, text, literal, self.line);
+        new_token.column = self.source[self.token_line_start..self.start]
+            .chars()
+            .count() as u32
+            + 1;
         self.tokens.push(new_token);
     }
 
+    // `line_start` is the idx just past the newline.
+    fn new_line(&mut self, line_start: usize) {
+        self.line += 1;
+        self.line_start = line_start;
+    }
+
     fn number(&mut self) {
         while let Some((_, ch)) = self.peek() {
             if !self.is_digit(ch) {
@@ -168,10 +233,10 @@ impl<'a> Scanner<'a> {
         }
 
         // Look for a fractional part.
-        if self.peek().map_or(false, |(_, ch)| ch == '.')
+        if self.peek().is_some_and(|(_, ch)| ch == '.')
             && self
                 .peek_next()
-                .map_or(false, |(_, next_ch)| self.is_digit(next_ch))
+                .is_some_and(|(_, next_ch)| self.is_digit(next_ch))
         {
             // Consume the "."
             self.advance();
@@ -194,7 +259,7 @@ impl<'a> Scanner<'a> {
     }
 
     fn string(&mut self) {
-        while let Some((_, ch)) = self.peek() {
+        while let Some((idx, ch)) = self.peek() {
             // Reached end of string, break loop
             if ch == '"' {
                 break;
@@ -202,7 +267,7 @@ impl<'a> Scanner<'a> {
 
             // We support multi line strings
             if ch == '\n' {
-                self.line += 1;
+                self.new_line(idx + 1);
             }
 
             // keep consuming string literal chars before the terminating "
@@ -223,7 +288,7 @@ impl<'a> Scanner<'a> {
         // and current is at idx after the last quote, so we will not trim within the byte.
         // NOTE: We don't support escape sequences as of now in strings.
         let value = &self.source[self.start + 1..self.current - 1];
-        self.add_token_with_literal(TokenType::String, Some(Literal::Str(value)));
+        self.add_token_with_literal(TokenType::String, Some(Literal::Str(value.into())));
     }
 
     fn identifier(&mut self) {
@@ -234,24 +299,10 @@ impl<'a> Scanner<'a> {
             self.advance();
         }
 
-        match &self.source[self.start..self.current] {
-            "and" => self.add_token(TokenType::And),
-            "class" => self.add_token(TokenType::Class),
-            "else" => self.add_token(TokenType::Else),
-            "false" => self.add_token(TokenType::False),
-            "for" => self.add_token(TokenType::For),
-            "fun" => self.add_token(TokenType::Fun),
-            "if" => self.add_token(TokenType::If),
-            "nil" => self.add_token(TokenType::Nil),
-            "or" => self.add_token(TokenType::Or),
-            "print" => self.add_token(TokenType::Print),
-            "return" => self.add_token(TokenType::Return),
-            "super" => self.add_token(TokenType::Super),
-            "this" => self.add_token(TokenType::This),
-            "true" => self.add_token(TokenType::True),
-            "var" => self.add_token(TokenType::Var),
-            "while" => self.add_token(TokenType::While),
-            _ => self.add_token(TokenType::Identifier),
+        let text = &self.source[self.start..self.current];
+        match KEYWORDS.iter().find(|(keyword, _)| *keyword == text) {
+            Some(&(_, c_type)) => self.add_token(c_type),
+            None => self.add_token(TokenType::Identifier),
         }
     }
 
@@ -290,11 +341,11 @@ impl<'a> Scanner<'a> {
     }
 
     fn is_digit(&self, c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
     fn is_alpha(&self, c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+        c.is_ascii_alphabetic() || c == '_'
     }
 
     fn is_alpha_numeric(&self, c: char) -> bool {
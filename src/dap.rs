@@ -0,0 +1,417 @@
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    cli_builder,
+    debugger::{Mode, Pauses},
+    embed::CapturedOutput,
+    interpreter::{Interpreter, StatementHook},
+    lox_error::LoxError,
+    lox_module::display_path,
+    runtime_error::Unwind,
+    statement::Stmt,
+};
+
+// Lox runs on a single thread, which clients still expect to be told about.
+const THREAD_ID: u64 = 1;
+
+// A Debug Adapter Protocol server over the given streams, normally stdin and stdout. The
+// script is launched once the client is done configuring, and runs on this thread: requests
+// are only read while it is paused, or once it has finished. `print` output is sent as
+// output events. Returns once the client disconnects.
+pub fn serve(input: impl BufRead + 'static, output: impl Write + 'static) -> Result<(), LoxError> {
+    let session = Rc::new(RefCell::new(Session {
+        input: Box::new(input),
+        output: Box::new(output),
+        seq: 0,
+        program: None,
+        args: Vec::new(),
+        pauses: Pauses::new(Mode::Continue),
+        script_output: CapturedOutput::new(),
+        stop_reason: "entry",
+        disconnected: false,
+    }));
+
+    // Configuring, until the client says it is done.
+    loop {
+        let Some(request) = session.borrow_mut().read_request()? else {
+            return Err(LoxError::Exit(1));
+        };
+        let mut session = session.borrow_mut();
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                session.respond(
+                    &request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsEvaluateForHovers": true,
+                    }),
+                )?;
+                session.event("initialized", json!({}))?;
+            }
+            "launch" => {
+                let arguments = &request["arguments"];
+                let Some(program) = arguments["program"].as_str() else {
+                    session.fail(&request, "Expected a 'program' to launch.")?;
+                    continue;
+                };
+                session.program = Some(program.to_string());
+                session.args = arguments["args"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|arg| arg.as_str().map(str::to_string))
+                    .collect();
+                if arguments["stopOnEntry"] == true {
+                    session.pauses.mode = Mode::Step;
+                }
+                session.respond(&request, Value::Null)?;
+            }
+            "configurationDone" => {
+                session.respond(&request, Value::Null)?;
+                if session.program.is_some() {
+                    break;
+                }
+            }
+            "disconnect" => {
+                session.respond(&request, Value::Null)?;
+                return Ok(());
+            }
+            _ => session.request(&request, None)?,
+        }
+    }
+
+    let exit_code = run(&session)?;
+
+    // The script is over, only questions about nothing and a disconnect are left.
+    let mut session = session.borrow_mut();
+    if !session.disconnected {
+        session.send_output()?;
+        session.event("exited", json!({ "exitCode": exit_code }))?;
+        session.event("terminated", json!({}))?;
+    }
+    while !session.disconnected {
+        let Some(request) = session.read_request()? else {
+            return Err(LoxError::Exit(1));
+        };
+        session.request(&request, None)?;
+    }
+    Ok(())
+}
+
+// Runs the launched script, returning its exit code. Errors are reported to the client.
+fn run(session: &Rc<RefCell<Session>>) -> Result<i32, LoxError> {
+    let (program, args, output) = {
+        let session = session.borrow();
+        let program = session.program.clone().unwrap_or_default();
+        (program, session.args.clone(), session.script_output.clone())
+    };
+
+    let mut lox = cli_builder().args(args).output(output).build();
+    lox.set_statement_hook(Hook(Rc::clone(session)));
+    let result = lox.run_file(&program);
+
+    let mut session = session.borrow_mut();
+    match result {
+        Ok(()) => Ok(0),
+        Err(LoxError::Exit(code)) => Ok(code),
+        Err(error) => {
+            session.send_output()?;
+            session.event(
+                "output",
+                json!({ "category": "stderr", "output": format!("{error}\n") }),
+            )?;
+            Ok(error.exit_code())
+        }
+    }
+}
+
+struct Session {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    // Numbers every message sent.
+    seq: u64,
+    program: Option<String>,
+    args: Vec<String>,
+    pauses: Pauses,
+    script_output: CapturedOutput,
+    // Why the script is paused next, for the stopped event.
+    stop_reason: &'static str,
+    disconnected: bool,
+}
+
+impl Session {
+    fn read_request(&mut self) -> Result<Option<Value>, LoxError> {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if self.input.read_line(&mut header)? == 0 {
+                return Ok(None);
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("Content-Length") {
+                    length = value.trim().parse().ok();
+                }
+            }
+        }
+
+        let length = length.ok_or_else(|| invalid_data("Missing Content-Length header."))?;
+        let mut body = vec![0; length];
+        self.input.read_exact(&mut body)?;
+        let request =
+            serde_json::from_slice(&body).map_err(|error| invalid_data(&error.to_string()))?;
+        Ok(Some(request))
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<(), LoxError> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        let body = message.to_string();
+        write!(self.output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.output.flush()?;
+        Ok(())
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> Result<(), LoxError> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Value, message: &str) -> Result<(), LoxError> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Result<(), LoxError> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    // Sends what the script printed since the last time.
+    fn send_output(&mut self) -> Result<(), LoxError> {
+        let output = self.script_output.take();
+        if output.is_empty() {
+            return Ok(());
+        }
+        self.event("output", json!({ "category": "stdout", "output": output }))
+    }
+
+    // Handles a request which doesn't resume the script, `paused` has the interpreter and
+    // line when it is paused.
+    fn request(
+        &mut self,
+        request: &Value,
+        paused: Option<(&mut Interpreter, u32)>,
+    ) -> Result<(), LoxError> {
+        let arguments = &request["arguments"];
+        let command = request["command"].as_str().unwrap_or_default();
+        let body = match (command, paused) {
+            ("setBreakpoints", _) => {
+                let lines: Vec<u32> = arguments["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|breakpoint| breakpoint["line"].as_u64())
+                    .map(|line| line as u32)
+                    .collect();
+                // Breakpoints are matched against the file of the running code, which
+                // is shown relative to the working directory.
+                let Some(path) = arguments["source"]["path"].as_str() else {
+                    return self.fail(request, "Expected the 'source' of the breakpoints.");
+                };
+                let file =
+                    fs::canonicalize(path).map_or(path.to_string(), |path| display_path(&path));
+                self.pauses
+                    .breakpoints
+                    .insert(file, lines.iter().copied().collect());
+                let breakpoints: Vec<Value> = lines
+                    .iter()
+                    .map(|line| json!({ "verified": true, "line": line }))
+                    .collect();
+                Ok(json!({ "breakpoints": breakpoints }))
+            }
+            ("threads", _) => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            ("disconnect", paused) => {
+                self.disconnected = true;
+                self.respond(request, Value::Null)?;
+                return match paused {
+                    // Stops the script.
+                    Some(_) => Err(LoxError::Exit(0)),
+                    None => Ok(()),
+                };
+            }
+            ("stackTrace", Some((interpreter, line))) => {
+                let program = self.program.clone().unwrap_or_default();
+                let frames: Vec<Value> = interpreter
+                    .stack_trace(line)
+                    .iter()
+                    .enumerate()
+                    .map(|(id, frame)| {
+                        // Frames in imported modules point at their own file.
+                        let path = frame
+                            .file()
+                            .filter(|file| Path::new(file) != Path::new(&program))
+                            .and_then(|file| fs::canonicalize(file).ok())
+                            .map_or(program.clone(), |path| path.display().to_string());
+                        json!({
+                            "id": id,
+                            "name": frame.function(),
+                            "line": frame.line(),
+                            "column": 1,
+                            "source": { "path": path },
+                        })
+                    })
+                    .collect();
+                Ok(json!({ "totalFrames": frames.len(), "stackFrames": frames }))
+            }
+            // Each frame has its locals and the globals, numbered from 1 as 0 means none.
+            ("scopes", Some(_)) => {
+                let frame = arguments["frameId"].as_u64().unwrap_or_default();
+                Ok(json!({
+                    "scopes": [
+                        { "name": "Locals", "variablesReference": frame * 2 + 1, "expensive": false },
+                        { "name": "Globals", "variablesReference": frame * 2 + 2, "expensive": false },
+                    ],
+                }))
+            }
+            ("variables", Some((interpreter, _))) => {
+                let reference = arguments["variablesReference"].as_u64().unwrap_or_default();
+                let frame = (reference.saturating_sub(1) / 2) as usize;
+                let variables = if reference % 2 == 1 {
+                    // Inner scopes shadow outer ones.
+                    let mut variables: Vec<(String, _)> = Vec::new();
+                    for scope in interpreter.frame_variables(frame) {
+                        for (name, value) in scope {
+                            if !variables.iter().any(|(seen, _)| *seen == name) {
+                                variables.push((name, value));
+                            }
+                        }
+                    }
+                    variables
+                } else {
+                    interpreter.globals()
+                };
+                let variables: Vec<Value> = variables
+                    .iter()
+                    .map(|(name, value)| {
+                        json!({ "name": name, "value": value.repr(), "variablesReference": 0 })
+                    })
+                    .collect();
+                Ok(json!({ "variables": variables }))
+            }
+            ("evaluate", Some((interpreter, _))) => {
+                let frame = arguments["frameId"].as_u64().unwrap_or_default() as usize;
+                let expression = arguments["expression"].as_str().unwrap_or_default();
+                let source = format!("{};", expression.trim_end_matches(';'));
+                let value = interpreter.evaluate_in_frame(&source, frame);
+                self.send_output()?;
+                match value {
+                    Ok(value) => Ok(json!({ "result": value.repr(), "variablesReference": 0 })),
+                    Err(LoxError::Runtime(error)) => Err(error.message().to_string()),
+                    Err(error) => Err(error.to_string()),
+                }
+            }
+            ("stackTrace" | "scopes" | "variables" | "evaluate", None) => {
+                Err(String::from("The script isn't paused."))
+            }
+            (command, _) => Err(format!("Unknown request '{command}'.")),
+        };
+
+        match body {
+            Ok(body) => self.respond(request, body),
+            Err(message) => self.fail(request, &message),
+        }
+    }
+
+    // Answers requests until one resumes the script.
+    fn pause(&mut self, interpreter: &mut Interpreter, line: u32) -> Result<(), LoxError> {
+        self.send_output()?;
+        let reason = if self.pauses.is_breakpoint(interpreter.current_file(), line) {
+            "breakpoint"
+        } else {
+            self.stop_reason
+        };
+        self.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        )?;
+
+        loop {
+            let Some(request) = self.read_request()? else {
+                // The client is gone, stop the script.
+                self.disconnected = true;
+                return Err(LoxError::Exit(1));
+            };
+            let depth = interpreter.call_depth();
+            let mode = match request["command"].as_str().unwrap_or_default() {
+                "continue" => Mode::Continue,
+                "next" => Mode::Next(depth),
+                "stepIn" => Mode::Step,
+                "stepOut" => Mode::Finish(depth),
+                _ => {
+                    self.request(&request, Some((interpreter, line)))?;
+                    continue;
+                }
+            };
+
+            self.pauses.mode = mode;
+            self.stop_reason = "step";
+            let body = match mode {
+                Mode::Continue => json!({ "allThreadsContinued": true }),
+                _ => Value::Null,
+            };
+            self.respond(&request, body)?;
+            return Ok(());
+        }
+    }
+}
+
+struct Hook(Rc<RefCell<Session>>);
+
+impl StatementHook for Hook {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+        line: u32,
+    ) -> Result<(), Unwind> {
+        let mut session = self.0.borrow_mut();
+        let depth = interpreter.call_depth();
+        let file = interpreter.current_file();
+        let result = if session.pauses.should_pause(file, line, depth, stmt) {
+            session.pause(interpreter, line)
+        } else {
+            session.send_output()
+        };
+
+        // The script can't carry on once the client is gone or talking to it failed.
+        result.map_err(|error| match error {
+            LoxError::Exit(code) => Unwind::Exit(code),
+            _ => Unwind::Exit(74),
+        })
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,348 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{self, BufRead, Write},
+    ptr,
+};
+
+use crate::{
+    interpreter::{Interpreter, StatementHook},
+    lox_error::LoxError,
+    parse,
+    runtime_error::Unwind,
+    statement::Stmt,
+};
+
+const PROMPT: &str = "(debug) ";
+
+const COMMANDS: [(&str, &str); 15] = [
+    ("break <line>", "Pause whenever the line is reached"),
+    ("delete <line>", "Remove the breakpoint on the line"),
+    ("continue", "Run until the next breakpoint"),
+    ("step", "Run to the next line, entering calls"),
+    ("next", "Run to the next line, stepping over calls"),
+    ("finish", "Run until the current function returns"),
+    ("backtrace", "Show the call stack"),
+    (
+        "frame <n>",
+        "Select a frame of the call stack for locals and print",
+    ),
+    ("locals", "Show the variables of the selected frame"),
+    (
+        "print <expr>",
+        "Evaluate an expression in the selected frame",
+    ),
+    (
+        "watch <expr>",
+        "Show the value of an expression on every pause",
+    ),
+    ("unwatch <n>", "Remove a watch expression"),
+    ("list", "Show the source around the current line"),
+    ("help", "Show this help"),
+    ("quit", "Stop the script"),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+    Continue,
+    Step,
+    // Pause once back at this call depth or above.
+    Next(usize),
+    Finish(usize),
+}
+
+// Where a script pauses, for `lox debug` and the debug adapter: at breakpoints, and after
+// steps as the mode says. A line is only paused at once even if it has several
+// statements, unless it is run again.
+pub struct Pauses {
+    // The lines of each file, named the way the interpreter shows it.
+    pub breakpoints: HashMap<String, BTreeSet<u32>>,
+    pub mode: Mode,
+    // Where the last statement ran, to tell a new line from another statement on the same one.
+    last: Option<(u32, usize, *const Stmt)>,
+}
+
+impl Pauses {
+    pub fn new(mode: Mode) -> Self {
+        Pauses {
+            breakpoints: HashMap::new(),
+            mode,
+            last: None,
+        }
+    }
+
+    pub fn is_breakpoint(&self, file: Option<&str>, line: u32) -> bool {
+        file.and_then(|file| self.breakpoints.get(file))
+            .is_some_and(|lines| lines.contains(&line))
+    }
+
+    // Called before every statement.
+    pub fn should_pause(
+        &mut self,
+        file: Option<&str>,
+        line: u32,
+        depth: usize,
+        stmt: &Stmt,
+    ) -> bool {
+        let stmt = ptr::from_ref(stmt);
+        let same_line = self.last.is_some_and(|(last_line, last_depth, last_stmt)| {
+            last_line == line && last_depth == depth && !ptr::eq(last_stmt, stmt)
+        });
+        self.last = Some((line, depth, stmt));
+        if same_line {
+            return false;
+        }
+
+        self.is_breakpoint(file, line)
+            || match self.mode {
+                Mode::Continue => false,
+                Mode::Step => true,
+                Mode::Next(paused_depth) => depth <= paused_depth,
+                Mode::Finish(paused_depth) => depth < paused_depth,
+            }
+    }
+}
+
+// The hook behind `lox debug`. It pauses before the first line, at breakpoints and after
+// steps, then reads commands from stdin until one resumes the script.
+pub struct Debugger {
+    // The script being debugged, which `break` and `delete` lines are in.
+    file: String,
+    lines: Vec<String>,
+    pauses: Pauses,
+    watches: Vec<String>,
+    frame: usize,
+    previous_command: String,
+}
+
+impl Debugger {
+    pub fn new(file: &str, source: &str) -> Self {
+        Debugger {
+            file: file.to_string(),
+            lines: source.lines().map(str::to_string).collect(),
+            pauses: Pauses::new(Mode::Step),
+            watches: Vec::new(),
+            frame: 0,
+            previous_command: String::new(),
+        }
+    }
+
+    fn pause(&mut self, interpreter: &mut Interpreter, line: u32) -> Result<(), Unwind> {
+        let _ = interpreter.flush_output();
+        self.frame = 0;
+
+        let stack = interpreter.stack_trace(line);
+        let reason = if self.pauses.is_breakpoint(interpreter.current_file(), line) {
+            "Breakpoint"
+        } else {
+            "Paused"
+        };
+        println!("{reason} at line {line} in {}.", stack[0].function());
+        self.show_line(line);
+        for index in 0..self.watches.len() {
+            self.show_watch(interpreter, index);
+        }
+
+        let stdin = io::stdin();
+        loop {
+            print!("{PROMPT}");
+            let _ = io::stdout().flush();
+
+            let mut input = String::new();
+            // Without more commands the script runs to its end.
+            if stdin.lock().read_line(&mut input).unwrap_or(0) == 0 {
+                println!();
+                self.pauses.mode = Mode::Continue;
+                self.pauses.breakpoints.clear();
+                return Ok(());
+            }
+
+            // An empty line repeats the previous command, handy for stepping.
+            let input = match input.trim() {
+                "" => self.previous_command.clone(),
+                input => input.to_string(),
+            };
+            self.previous_command.clone_from(&input);
+
+            let (command, argument) = input.split_once(' ').unwrap_or((&input, ""));
+            let argument = argument.trim();
+            let depth = interpreter.call_depth();
+            let mode = match command {
+                "c" | "continue" => Mode::Continue,
+                "s" | "step" => Mode::Step,
+                "n" | "next" => Mode::Next(depth),
+                "finish" => Mode::Finish(depth),
+                "q" | "quit" => return Err(Unwind::Exit(0)),
+                command => {
+                    if let Err(error) = self.command(interpreter, line, command, argument) {
+                        println!("{}", error_message(&error));
+                    }
+                    continue;
+                }
+            };
+            self.pauses.mode = mode;
+            return Ok(());
+        }
+    }
+
+    // Commands which don't resume the script.
+    fn command(
+        &mut self,
+        interpreter: &mut Interpreter,
+        line: u32,
+        command: &str,
+        argument: &str,
+    ) -> Result<(), LoxError> {
+        match command {
+            "b" | "break" => {
+                let line = self.line_argument(argument)?;
+                self.pauses
+                    .breakpoints
+                    .entry(self.file.clone())
+                    .or_default()
+                    .insert(line);
+                println!("Breakpoint at line {line}.");
+            }
+            "d" | "delete" => {
+                let line = self.line_argument(argument)?;
+                let removed = self
+                    .pauses
+                    .breakpoints
+                    .get_mut(&self.file)
+                    .is_some_and(|lines| lines.remove(&line));
+                if !removed {
+                    return Err(LoxError::Usage(format!("No breakpoint at line {line}.")));
+                }
+            }
+            "bt" | "backtrace" => {
+                for (index, frame) in interpreter.stack_trace(line).iter().enumerate() {
+                    let marker = if index == self.frame { '>' } else { ' ' };
+                    println!("{marker} #{index} {frame}");
+                }
+            }
+            "f" | "frame" => {
+                let stack = interpreter.stack_trace(line);
+                let frame = argument
+                    .parse()
+                    .ok()
+                    .filter(|frame| *frame < stack.len())
+                    .ok_or_else(|| LoxError::Usage(format!("There is no frame '{argument}'.")))?;
+                self.frame = frame;
+                println!("#{frame} {}", stack[frame]);
+            }
+            "locals" => {
+                let scopes = interpreter.frame_variables(self.frame);
+                if scopes.iter().all(Vec::is_empty) {
+                    println!("No locals.");
+                }
+                // Inner scopes are indented less, they shadow the outer ones.
+                for (depth, scope) in scopes.iter().enumerate() {
+                    for (name, value) in scope {
+                        println!("{}{name} = {}", "  ".repeat(depth), value.repr());
+                    }
+                }
+            }
+            "p" | "print" => {
+                let value = interpreter.evaluate_in_frame(&expression(argument)?, self.frame)?;
+                println!("{}", value.repr());
+            }
+            "watch" => {
+                expression(argument)?;
+                self.watches.push(argument.to_string());
+                self.show_watch(interpreter, self.watches.len() - 1);
+            }
+            "unwatch" => {
+                let index = argument
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| (1..=self.watches.len()).contains(index))
+                    .ok_or_else(|| LoxError::Usage(format!("There is no watch '{argument}'.")))?;
+                self.watches.remove(index - 1);
+            }
+            "l" | "list" => {
+                let first = line.saturating_sub(5).max(1);
+                for number in first..=line + 5 {
+                    if let Some(text) = self.lines.get(number as usize - 1) {
+                        let marker = if number == line { "->" } else { "  " };
+                        println!("{marker}{number:>4} | {text}");
+                    }
+                }
+            }
+            "h" | "help" => {
+                for (usage, description) in COMMANDS {
+                    println!("  {usage:<16}{description}");
+                }
+            }
+            _ => {
+                return Err(LoxError::Usage(format!(
+                    "Unknown command '{command}', see help."
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn line_argument(&self, argument: &str) -> Result<u32, LoxError> {
+        argument
+            .parse()
+            .ok()
+            .filter(|line| (1..=self.lines.len() as u32).contains(line))
+            .ok_or_else(|| LoxError::Usage(format!("There is no line '{argument}'.")))
+    }
+
+    fn show_line(&self, line: u32) {
+        if let Some(text) = self.lines.get(line as usize - 1) {
+            println!("{line:>4} | {text}");
+        }
+    }
+
+    // Watches are evaluated in the innermost frame, names it doesn't have are errors.
+    fn show_watch(&self, interpreter: &mut Interpreter, index: usize) {
+        let watch = &self.watches[index];
+        let value = expression(watch).and_then(|source| interpreter.evaluate_in_frame(&source, 0));
+        match value {
+            Ok(value) => println!("  {}: {watch} = {}", index + 1, value.repr()),
+            Err(error) => println!("  {}: {watch} = <{}>", index + 1, error_message(&error)),
+        }
+    }
+}
+
+impl StatementHook for Debugger {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+        line: u32,
+    ) -> Result<(), Unwind> {
+        let depth = interpreter.call_depth();
+        if self
+            .pauses
+            .should_pause(interpreter.current_file(), line, depth, stmt)
+        {
+            self.pause(interpreter, line)?;
+        }
+        Ok(())
+    }
+}
+
+// The `;` after an expression is optional.
+fn expression(source: &str) -> Result<String, LoxError> {
+    if source.is_empty() {
+        return Err(LoxError::Usage(String::from("Expected an expression.")));
+    }
+    let statement = format!("{source};");
+    match parse(&statement) {
+        Ok(_) => Ok(statement),
+        Err(errors) => match parse(source) {
+            Ok(_) => Ok(source.to_string()),
+            Err(_) => Err(LoxError::Compile(errors)),
+        },
+    }
+}
+
+// The line of a runtime error is of the evaluated code, not of the script.
+fn error_message(error: &LoxError) -> String {
+    match error {
+        LoxError::Runtime(error) => error.message().to_string(),
+        error => error.to_string(),
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{
+    capabilities::Capabilities,
+    compile,
+    interpreter::{Interpreter, StatementHook},
+    limits::Limits,
+    lox_callable::NativeFunction,
+    lox_class::LoxClass,
+    lox_convert::{FromLox, FromLoxError, IntoLox},
+    lox_error::LoxError,
+    native_class::ClassBuilder,
+    runtime_error::RuntimeError,
+    value::Value,
+};
+
+// Runs Lox code from a Rust program. State persists between calls, so globals defined by
+// one `eval` are visible to the next.
+//
+//     let mut lox = Lox::new();
+//     lox.define_native("double", 1, |args| match &args[0] {
+//         Value::Number(n) => Ok(Value::Number(n * 2.0)),
+//         _ => Err("Expected a number.".to_string()),
+//     });
+//     let value = lox.eval("double(21);")?;
+pub struct Lox {
+    interpreter: Interpreter,
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lox {
+    // No I/O capabilities and default limits, see `builder` to change them.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> LoxBuilder {
+        LoxBuilder::default()
+    }
+
+    // Runs the source and returns the value of its last statement if that is an expression,
+    // nil otherwise.
+    pub fn eval(&mut self, source: &str) -> Result<Value, LoxError> {
+        let statements = compile(source, &mut self.interpreter).map_err(LoxError::Compile)?;
+        let result = self.interpreter.interpret(&statements);
+        self.flush_output(result)
+    }
+
+    // Runs a script file as the main module, its imports are resolved relative to it.
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<(), LoxError> {
+        let result = self.interpreter.run_main(path.as_ref());
+        self.flush_output(result)
+    }
+
+    // Redirects `print` from now on.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.interpreter.set_output(Box::new(output));
+    }
+
+    // Everything printed is written out before returning, so the host can report errors
+    // after the output which led to them.
+    fn flush_output<T>(&mut self, result: Result<T, LoxError>) -> Result<T, LoxError> {
+        let flushed = self.interpreter.flush_output();
+        let value = result?;
+        flushed?;
+        Ok(value)
+    }
+
+    // Runs the hook before every statement from now on, the way `lox debug` pauses scripts.
+    pub fn set_statement_hook(&mut self, hook: impl StatementHook + 'static) {
+        self.interpreter.set_statement_hook(Some(Box::new(hook)));
+    }
+
+    pub fn define_global(&mut self, name: &str, value: impl IntoLox) {
+        self.interpreter.define_global(name, value.into_lox());
+    }
+
+    // Defines the class under its own name and returns it, for creating instances from Rust
+    // with `native_class::instance`.
+    pub fn define_class<T: 'static>(&mut self, class: ClassBuilder<T>) -> Rc<LoxClass> {
+        let class = class.build();
+        self.define_global(&class.name, Value::Class(Rc::clone(&class)));
+        class
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.interpreter.get_global(name)
+    }
+
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.interpreter.globals()
+    }
+
+    pub fn global_names(&self) -> Vec<String> {
+        self.interpreter.global_names()
+    }
+
+    // Reads a global converted to a Rust type, None if it isn't defined.
+    pub fn get<T: FromLox>(&self, name: &str) -> Option<Result<T, FromLoxError>> {
+        self.get_global(name).map(|value| T::from_lox(&value))
+    }
+
+    // Exposes a Rust closure as a global Lox function. Returning an error raises a Lox
+    // runtime error with that message, which scripts can catch.
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Value]) -> Result<Value, String> + 'static,
+    ) {
+        let native = NativeFunction::new(arity, move |_, paren, arguments| {
+            function(&arguments).map_err(|message| RuntimeError::new(paren, message).into())
+        });
+        self.define_global(name, Value::Native(Rc::new(native)));
+    }
+}
+
+// An output sink which keeps everything written to it, for tests and hosts which want the
+// script's output as a string. Clones share the same buffer.
+//
+//     let output = CapturedOutput::new();
+//     let mut lox = Lox::builder().output(output.clone()).build();
+//     lox.eval("print 1 + 2;")?;
+//     assert_eq!(output.contents(), "3\n");
+#[derive(Clone, Default)]
+pub struct CapturedOutput {
+    buffer: Rc<RefCell<Vec<u8>>>,
+}
+
+impl CapturedOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.borrow()).into_owned()
+    }
+
+    // Returns what was written so far and empties the buffer.
+    pub fn take(&self) -> String {
+        let buffer = std::mem::take(&mut *self.buffer.borrow_mut());
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+}
+
+impl Write for CapturedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct LoxBuilder {
+    capabilities: Capabilities,
+    limits: Limits,
+    output: Option<Box<dyn Write>>,
+    args: Vec<String>,
+}
+
+impl LoxBuilder {
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    // Where `print` writes to, stdout by default.
+    pub fn output(mut self, output: impl Write + 'static) -> Self {
+        self.output = Some(Box::new(output));
+        self
+    }
+
+    // What the script sees as `os.args()`.
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    pub fn build(self) -> Lox {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_capabilities(self.capabilities);
+        interpreter.set_limits(self.limits);
+        interpreter.set_args(self.args);
+        if let Some(output) = self.output {
+            interpreter.set_output(output);
+        }
+        Lox { interpreter }
+    }
+}
@@ -0,0 +1,96 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{runtime_error::RuntimeError, token::Token, value::Value};
+
+#[derive(Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    // The variables of this scope itself, not of the ones enclosing it.
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.values.get(name.lexeme.as_ref()) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(RuntimeError::new(
+                name,
+                format!("Undefined variable '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(name.lexeme.as_ref()) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(RuntimeError::new(
+                name,
+                format!("Undefined variable '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    // The resolver guarantees the variable exists `distance` scopes up.
+    pub fn get_at(env: &Rc<RefCell<Environment>>, distance: usize, name: &str) -> Value {
+        Self::ancestor(env, distance)
+            .borrow()
+            .values
+            .get(name)
+            .cloned()
+            .unwrap_or(Value::Nil)
+    }
+
+    pub fn assign_at(env: &Rc<RefCell<Environment>>, distance: usize, name: &Token, value: Value) {
+        Self::ancestor(env, distance)
+            .borrow_mut()
+            .values
+            .insert(name.lexeme.to_string(), value);
+    }
+
+    fn ancestor(env: &Rc<RefCell<Environment>>, distance: usize) -> Rc<RefCell<Environment>> {
+        let mut environment = Rc::clone(env);
+        for _ in 0..distance {
+            let enclosing = environment
+                .borrow()
+                .enclosing
+                .clone()
+                .expect("Resolver computed a distance beyond the outermost scope");
+            environment = enclosing;
+        }
+        environment
+    }
+}
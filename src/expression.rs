@@ -1,18 +1,167 @@
-use crate::token::{Literal, Token};
+use std::{
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-pub struct BinaryExpr<'a> {
-    pub(super) left: Box<Expr<'a>>,
-    pub(super) operator: Token<'a>,
-    pub(super) right: Box<Expr<'a>>,
+use crate::{
+    statement::FunctionDecl,
+    token::{Literal, Span, Token},
+};
+
+// Every expression which refers to a variable gets a unique id, so the resolver
+// can tell the interpreter how far away (in scopes) that variable lives.
+static NEXT_EXPR_ID: AtomicUsize = AtomicUsize::new(0);
+
+fn next_expr_id() -> usize {
+    NEXT_EXPR_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+pub struct AssignExpr {
+    pub(super) id: usize,
+    pub(super) name: Token,
+    pub(super) value: Box<Expr>,
+}
+
+pub struct BinaryExpr {
+    pub(super) left: Box<Expr>,
+    pub(super) operator: Token,
+    pub(super) right: Box<Expr>,
+}
+
+pub struct CallExpr {
+    pub(super) callee: Box<Expr>,
+    pub(super) paren: Token, // closing paren, used to report the line of the call
+    pub(super) arguments: Vec<Expr>,
+}
+
+pub struct GetExpr {
+    pub(super) object: Box<Expr>,
+    pub(super) name: Token,
+}
+
+pub struct IndexExpr {
+    pub(super) object: Box<Expr>,
+    pub(super) bracket: Token, // opening bracket, used to report the line of the access
+    pub(super) index: Box<Expr>,
+    pub(super) span: Span, // from `[` to `]`, for index errors
+}
+
+pub struct ListExpr {
+    pub(super) bracket: Token,
+    pub(super) elements: Vec<Expr>,
+}
+
+pub struct LiteralExpr {
+    // `true`, `false`, `nil`, a number or a string, or `for` for the condition of a loop
+    // which left it out.
+    pub(super) token: Token,
+    pub(super) value: Literal,
+}
+
+pub struct LogicalExpr {
+    pub(super) left: Box<Expr>,
+    pub(super) operator: Token,
+    pub(super) right: Box<Expr>,
+}
+
+pub struct MapExpr {
+    pub(super) brace: Token, // used to report unhashable keys
+    pub(super) entries: Vec<(Expr, Expr)>,
+}
+
+pub struct SetExpr {
+    pub(super) object: Box<Expr>,
+    pub(super) name: Token,
+    pub(super) value: Box<Expr>,
+}
+
+pub struct SetIndexExpr {
+    pub(super) object: Box<Expr>,
+    pub(super) bracket: Token,
+    pub(super) index: Box<Expr>,
+    pub(super) span: Span,
+    pub(super) value: Box<Expr>,
+}
+
+pub struct SuperExpr {
+    pub(super) id: usize,
+    pub(super) keyword: Token,
+    pub(super) method: Token,
+}
+
+pub struct ThisExpr {
+    pub(super) id: usize,
+    pub(super) keyword: Token,
+}
+
+pub struct UnaryExpr {
+    pub(super) operator: Token,
+    pub(super) right: Box<Expr>,
+}
+
+pub struct VariableExpr {
+    pub(super) id: usize,
+    pub(super) name: Token,
+}
+
+impl AssignExpr {
+    fn new(name: Token, value: Expr) -> Self {
+        Self {
+            id: next_expr_id(),
+            name,
+            value: Box::new(value),
+        }
+    }
+}
+
+impl BinaryExpr {
+    fn new(left: Expr, operator: Token, right: Expr) -> Self {
+        Self {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }
+    }
+}
+
+impl CallExpr {
+    fn new(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Self {
+        Self {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        }
+    }
+}
+
+impl GetExpr {
+    fn new(object: Expr, name: Token) -> Self {
+        Self {
+            object: Box::new(object),
+            name,
+        }
+    }
+}
+
+impl IndexExpr {
+    fn new(object: Expr, bracket: Token, index: Expr, span: Span) -> Self {
+        Self {
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+            span,
+        }
+    }
 }
 
-pub struct UnaryExpr<'a> {
-    pub(super) operator: Token<'a>,
-    pub(super) right: Box<Expr<'a>>,
+impl ListExpr {
+    fn new(bracket: Token, elements: Vec<Expr>) -> Self {
+        Self { bracket, elements }
+    }
 }
 
-impl<'a> BinaryExpr<'a> {
-    fn new(left: Expr<'a>, operator: Token<'a>, right: Expr<'a>) -> Self {
+impl LogicalExpr {
+    fn new(left: Expr, operator: Token, right: Expr) -> Self {
         Self {
             left: Box::new(left),
             operator,
@@ -21,8 +170,55 @@ impl<'a> BinaryExpr<'a> {
     }
 }
 
-impl<'a> UnaryExpr<'a> {
-    fn new(operator: Token<'a>, right: Expr<'a>) -> Self {
+impl MapExpr {
+    fn new(brace: Token, entries: Vec<(Expr, Expr)>) -> Self {
+        Self { brace, entries }
+    }
+}
+
+impl SetExpr {
+    fn new(object: Expr, name: Token, value: Expr) -> Self {
+        Self {
+            object: Box::new(object),
+            name,
+            value: Box::new(value),
+        }
+    }
+}
+
+impl SetIndexExpr {
+    fn new(object: Expr, bracket: Token, index: Expr, span: Span, value: Expr) -> Self {
+        Self {
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+            span,
+            value: Box::new(value),
+        }
+    }
+}
+
+impl SuperExpr {
+    fn new(keyword: Token, method: Token) -> Self {
+        Self {
+            id: next_expr_id(),
+            keyword,
+            method,
+        }
+    }
+}
+
+impl ThisExpr {
+    fn new(keyword: Token) -> Self {
+        Self {
+            id: next_expr_id(),
+            keyword,
+        }
+    }
+}
+
+impl UnaryExpr {
+    fn new(operator: Token, right: Expr) -> Self {
         Self {
             operator,
             right: Box::new(right),
@@ -30,46 +226,134 @@ impl<'a> UnaryExpr<'a> {
     }
 }
 
-pub enum Expr<'a> {
-    Binary(Box<BinaryExpr<'a>>),
-    Unary(Box<UnaryExpr<'a>>),
-    Grouping(Box<Expr<'a>>),
-    Literal(Literal<'a>),
+impl VariableExpr {
+    fn new(name: Token) -> Self {
+        Self {
+            id: next_expr_id(),
+            name,
+        }
+    }
 }
 
-impl<'a> Expr<'a> {
-    pub fn new_binary(left: Expr<'a>, operator: Token<'a>, right: Expr<'a>) -> Self {
+pub enum Expr {
+    Assign(Box<AssignExpr>),
+    Binary(Box<BinaryExpr>),
+    Call(Box<CallExpr>),
+    Get(Box<GetExpr>),
+    Grouping(Box<Expr>),
+    Index(Box<IndexExpr>),
+    // Anonymous function, `fun (a, b) { ... }` or `(a, b) => a + b`.
+    // Shares its declaration type with named functions so both capture closures the same way.
+    Lambda(Rc<FunctionDecl>),
+    List(Box<ListExpr>),
+    Literal(Box<LiteralExpr>),
+    Logical(Box<LogicalExpr>),
+    Map(Box<MapExpr>),
+    Set(Box<SetExpr>),
+    SetIndex(Box<SetIndexExpr>),
+    Super(Box<SuperExpr>),
+    This(Box<ThisExpr>),
+    Unary(Box<UnaryExpr>),
+    Variable(Box<VariableExpr>),
+}
+
+impl Expr {
+    // The line of a token kept in the tree for the expression.
+    pub fn line(&self) -> u32 {
+        match self {
+            Expr::Assign(assign) => assign.name.line,
+            Expr::Binary(binary) => binary.operator.line,
+            Expr::Call(call) => call.paren.line,
+            Expr::Get(get) => get.name.line,
+            Expr::Grouping(expr) => expr.line(),
+            Expr::Index(index) => index.bracket.line,
+            Expr::Lambda(declaration) => declaration.keyword.line,
+            Expr::List(list) => list.bracket.line,
+            Expr::Literal(literal) => literal.token.line,
+            Expr::Logical(logical) => logical.operator.line,
+            Expr::Map(map) => map.brace.line,
+            Expr::Set(set) => set.name.line,
+            Expr::SetIndex(set_index) => set_index.bracket.line,
+            Expr::Super(super_expr) => super_expr.keyword.line,
+            Expr::This(this) => this.keyword.line,
+            Expr::Unary(unary) => unary.operator.line,
+            Expr::Variable(variable) => variable.name.line,
+        }
+    }
+
+    pub fn new_assign(name: Token, value: Expr) -> Self {
+        Expr::Assign(Box::new(AssignExpr::new(name, value)))
+    }
+
+    pub fn new_binary(left: Expr, operator: Token, right: Expr) -> Self {
         Expr::Binary(Box::new(BinaryExpr::new(left, operator, right)))
     }
 
-    pub fn new_unary(operator: Token<'a>, right: Expr<'a>) -> Self {
-        Expr::Unary(Box::new(UnaryExpr::new(operator, right)))
+    pub fn new_call(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Self {
+        Expr::Call(Box::new(CallExpr::new(callee, paren, arguments)))
+    }
+
+    pub fn new_get(object: Expr, name: Token) -> Self {
+        Expr::Get(Box::new(GetExpr::new(object, name)))
     }
 
-    pub fn new_grouping(expr: Expr<'a>) -> Self {
+    pub fn new_grouping(expr: Expr) -> Self {
         Expr::Grouping(Box::new(expr))
     }
 
-    pub fn new_literal(literal: Literal<'a>) -> Self {
-        Expr::Literal(literal)
+    pub fn new_index(object: Expr, bracket: Token, index: Expr, span: Span) -> Self {
+        Expr::Index(Box::new(IndexExpr::new(object, bracket, index, span)))
+    }
+
+    pub fn new_lambda(function: FunctionDecl) -> Self {
+        Expr::Lambda(Rc::new(function))
+    }
+
+    pub fn new_list(bracket: Token, elements: Vec<Expr>) -> Self {
+        Expr::List(Box::new(ListExpr::new(bracket, elements)))
+    }
+
+    pub fn new_literal(token: Token, value: Literal) -> Self {
+        Expr::Literal(Box::new(LiteralExpr { token, value }))
+    }
+
+    pub fn new_logical(left: Expr, operator: Token, right: Expr) -> Self {
+        Expr::Logical(Box::new(LogicalExpr::new(left, operator, right)))
+    }
+
+    pub fn new_map(brace: Token, entries: Vec<(Expr, Expr)>) -> Self {
+        Expr::Map(Box::new(MapExpr::new(brace, entries)))
+    }
+
+    pub fn new_set(object: Expr, name: Token, value: Expr) -> Self {
+        Expr::Set(Box::new(SetExpr::new(object, name, value)))
+    }
+
+    pub fn new_set_index(
+        object: Expr,
+        bracket: Token,
+        index: Expr,
+        span: Span,
+        value: Expr,
+    ) -> Self {
+        Expr::SetIndex(Box::new(SetIndexExpr::new(
+            object, bracket, index, span, value,
+        )))
     }
-}
 
-// !NOTE: Not sure if I need visitor pattern in Rust.
-// pub trait Visitor<'a, T> {
-//     fn visit_binary(&mut self, binary_expr: &BinaryExpr<'a>) -> T;
-//     fn visit_literal(&mut self, value: &Literal<'a>) -> T;
-//     fn visit_grouping(&mut self, expr: &Expr<'a>) -> T;
-//     fn visit_unary(&mut self, expr: &UnaryExpr<'a>) -> T;
-// }
+    pub fn new_super(keyword: Token, method: Token) -> Self {
+        Expr::Super(Box::new(SuperExpr::new(keyword, method)))
+    }
 
-// impl<'a> Expr<'a> {
-//     pub fn accept<T>(&self, visitor: &mut dyn Visitor<'a, T>) -> T {
-//         match self {
-//             Expr::Binary(expr) => visitor.visit_binary(expr),
-//             Expr::Grouping(expr) => visitor.visit_grouping(expr),
-//             Expr::Literal(expr) => visitor.visit_literal(expr),
-//             Expr::Unary(expr) => visitor.visit_unary(expr),
-//         }
-//     }
-// }
+    pub fn new_this(keyword: Token) -> Self {
+        Expr::This(Box::new(ThisExpr::new(keyword)))
+    }
+
+    pub fn new_unary(operator: Token, right: Expr) -> Self {
+        Expr::Unary(Box::new(UnaryExpr::new(operator, right)))
+    }
+
+    pub fn new_variable(name: Token) -> Self {
+        Expr::Variable(Box::new(VariableExpr::new(name)))
+    }
+}
@@ -0,0 +1,406 @@
+use crate::{
+    custom_scanner::Scanner, lox_error::SyntaxError, parse, token::Token, token_type::TokenType,
+};
+
+const INDENT: &str = "  ";
+const MAX_WIDTH: usize = 100;
+
+// Re-emits the source in the canonical style: two space indents, one statement per line,
+// spaces around binary operators, opening braces on the line of their statement and lines
+// longer than MAX_WIDTH broken inside brackets. Comments and single blank lines between
+// statements are kept. Code which doesn't parse is left alone.
+//
+// Works on the tokens rather than the syntax tree, which drops comments and desugars `for`.
+pub fn format(source: &str) -> Result<String, Vec<SyntaxError>> {
+    parse(source)?;
+
+    let mut scanner = Scanner::with_comments(source);
+    let tokens = scanner.scan_tokens().to_vec();
+
+    let mut formatter = Formatter::default();
+    for token in tokens.iter().filter(|token| token.c_type != TokenType::Eof) {
+        formatter.token(token);
+    }
+    Ok(formatter.finish())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Bracket {
+    Paren,
+    ForHeader, // the parens after `for`, where `;` doesn't end the line
+    Square,
+    Block,
+    Map,
+    Import, // `import { names } from ...`
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PieceKind {
+    Open,
+    Close,
+    Comma,
+    Other,
+}
+
+// A token as it will be printed, on a line which isn't broken up yet.
+struct Piece {
+    text: String,
+    space_before: bool,
+    kind: PieceKind,
+}
+
+#[derive(Default)]
+struct Formatter {
+    output: String,
+    pieces: Vec<Piece>,
+    line_indent: usize,
+    indent: usize,
+    brackets: Vec<Bracket>,
+    previous: Option<Token>,
+    previous_is_unary: bool,
+    previous_end_line: u32,
+    // The statement so far ended, the next token starts a new line.
+    end_line: bool,
+    // A statement was cut by a line comment, its next line is indented once more.
+    in_statement: bool,
+}
+
+impl Formatter {
+    fn token(&mut self, token: &Token) {
+        let start_line = token.line - token.lexeme.matches('\n').count() as u32;
+
+        if token.c_type == TokenType::Comment {
+            self.comment(token, start_line);
+            return;
+        }
+
+        let closes_block =
+            token.c_type == TokenType::RightBrace && self.brackets.last() == Some(&Bracket::Block);
+        let empty_block = closes_block
+            && self
+                .previous
+                .as_ref()
+                .is_some_and(|previous| previous.c_type == TokenType::LeftBrace)
+            && self.pieces.last().is_some_and(|piece| piece.text == "{");
+
+        if closes_block {
+            self.brackets.pop();
+            self.indent -= 1;
+            if !empty_block {
+                self.flush_line();
+            }
+        } else if self.end_line && !self.continues_line(token) {
+            self.flush_line();
+        }
+        self.end_line = false;
+
+        if self.pieces.is_empty() {
+            if !closes_block {
+                self.blank_line(start_line);
+            }
+            self.line_indent = self.indent + usize::from(self.in_statement);
+        }
+
+        let space_before = !self.pieces.is_empty() && !empty_block && self.space_before(token);
+        let kind = self.update_brackets(token, closes_block);
+        self.pieces.push(Piece {
+            text: token.lexeme.to_string(),
+            space_before,
+            kind,
+        });
+
+        self.in_statement = true;
+        match token.c_type {
+            TokenType::SemiColon if self.brackets.last() != Some(&Bracket::ForHeader) => {
+                self.end_statement()
+            }
+            TokenType::LeftBrace if self.brackets.last() == Some(&Bracket::Block) => {
+                self.end_statement()
+            }
+            TokenType::RightBrace if closes_block => self.end_statement(),
+            _ => (),
+        }
+
+        self.previous_is_unary = match token.c_type {
+            TokenType::Minus => !self.previous.as_ref().is_some_and(ends_value),
+            TokenType::Bang => true,
+            _ => false,
+        };
+        self.previous = Some(token.clone());
+        self.previous_end_line = token.line;
+    }
+
+    fn end_statement(&mut self) {
+        self.end_line = true;
+        self.in_statement = false;
+    }
+
+    // A comment on the line of the code before it stays there, others get their own line.
+    fn comment(&mut self, token: &Token, start_line: u32) {
+        let text = token.lexeme.trim_end().to_string();
+        let is_line_comment = text.starts_with("//");
+
+        if !self.pieces.is_empty() && start_line == self.previous_end_line {
+            self.pieces.push(Piece {
+                text,
+                space_before: true,
+                kind: PieceKind::Other,
+            });
+            if is_line_comment {
+                self.flush_line();
+            }
+        } else {
+            self.flush_line();
+            self.blank_line(start_line);
+            self.line_indent = self.indent + usize::from(self.in_statement);
+            self.pieces.push(Piece {
+                text,
+                space_before: false,
+                kind: PieceKind::Other,
+            });
+            if is_line_comment || !self.in_statement {
+                self.flush_line();
+            }
+        }
+        self.previous_end_line = token.line;
+    }
+
+    // Keeps a blank line the source had before a statement or comment, never more than one.
+    fn blank_line(&mut self, start_line: u32) {
+        if start_line > self.previous_end_line + 1
+            && !self.output.is_empty()
+            && !self.output.ends_with("{\n")
+            && !self.output.ends_with("\n\n")
+        {
+            self.output.push('\n');
+        }
+    }
+
+    // What may follow a block's closing brace on the same line.
+    fn continues_line(&self, token: &Token) -> bool {
+        let after_block = self
+            .previous
+            .as_ref()
+            .is_some_and(|previous| previous.c_type == TokenType::RightBrace);
+
+        after_block
+            && matches!(
+                token.c_type,
+                TokenType::Else
+                    | TokenType::Catch
+                    | TokenType::Finally
+                    | TokenType::SemiColon
+                    | TokenType::RightParen
+                    | TokenType::RightBracket
+                    | TokenType::Comma
+            )
+    }
+
+    fn space_before(&self, token: &Token) -> bool {
+        let Some(previous) = &self.previous else {
+            return false;
+        };
+
+        if matches!(
+            token.c_type,
+            TokenType::Comma
+                | TokenType::SemiColon
+                | TokenType::Dot
+                | TokenType::Colon
+                | TokenType::RightParen
+                | TokenType::RightBracket
+        ) {
+            return false;
+        }
+
+        match previous.c_type {
+            TokenType::LeftParen | TokenType::LeftBracket | TokenType::Dot | TokenType::Bang => {
+                return false
+            }
+            TokenType::Minus if self.previous_is_unary => return false,
+            TokenType::LeftBrace if self.brackets.last() == Some(&Bracket::Map) => return false,
+            _ => (),
+        }
+
+        match token.c_type {
+            // Calls and indexing hug their callee, groupings and list literals don't.
+            TokenType::LeftParen | TokenType::LeftBracket => !ends_value(previous),
+            TokenType::RightBrace => self.brackets.last() != Some(&Bracket::Map),
+            _ => true,
+        }
+    }
+
+    // Tracks the brackets the token opens or closes, returning how it can be broken up.
+    fn update_brackets(&mut self, token: &Token, closed_block: bool) -> PieceKind {
+        let previous = self.previous.as_ref().map(|previous| previous.c_type);
+        match token.c_type {
+            TokenType::LeftParen if previous == Some(TokenType::For) => {
+                self.brackets.push(Bracket::ForHeader);
+                PieceKind::Open
+            }
+            TokenType::LeftParen => {
+                self.brackets.push(Bracket::Paren);
+                PieceKind::Open
+            }
+            TokenType::LeftBracket => {
+                self.brackets.push(Bracket::Square);
+                PieceKind::Open
+            }
+            TokenType::LeftBrace if previous == Some(TokenType::Import) => {
+                self.brackets.push(Bracket::Import);
+                PieceKind::Open
+            }
+            TokenType::LeftBrace if previous.is_some_and(expects_expression) => {
+                self.brackets.push(Bracket::Map);
+                PieceKind::Open
+            }
+            TokenType::LeftBrace => {
+                self.brackets.push(Bracket::Block);
+                self.indent += 1;
+                PieceKind::Other
+            }
+            TokenType::RightBrace if closed_block => PieceKind::Other,
+            TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace => {
+                self.brackets.pop();
+                PieceKind::Close
+            }
+            TokenType::Comma => PieceKind::Comma,
+            _ => PieceKind::Other,
+        }
+    }
+
+    fn flush_line(&mut self) {
+        if self.pieces.is_empty() {
+            return;
+        }
+        let pieces = std::mem::take(&mut self.pieces);
+        for line in render(&pieces, self.line_indent) {
+            self.output.push_str(&line);
+            self.output.push('\n');
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush_line();
+        self.output
+    }
+}
+
+// Tokens after which a `-` is binary and a `(` or `[` is a call or an index.
+fn ends_value(token: &Token) -> bool {
+    matches!(
+        token.c_type,
+        TokenType::Identifier
+            | TokenType::Number
+            | TokenType::String
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil
+            | TokenType::This
+            | TokenType::Super
+            | TokenType::RightParen
+            | TokenType::RightBracket
+            | TokenType::RightBrace
+    )
+}
+
+// Tokens after which a `{` starts a map literal rather than a block.
+fn expects_expression(c_type: TokenType) -> bool {
+    matches!(
+        c_type,
+        TokenType::LeftParen
+            | TokenType::LeftBracket
+            | TokenType::Colon
+            | TokenType::Comma
+            | TokenType::Minus
+            | TokenType::Plus
+            | TokenType::Slash
+            | TokenType::Star
+            | TokenType::Bang
+            | TokenType::BangEqual
+            | TokenType::Equal
+            | TokenType::EqualEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::And
+            | TokenType::Or
+            | TokenType::In
+            | TokenType::Print
+            | TokenType::Return
+            | TokenType::Throw
+    )
+}
+
+// Lays out one line, breaking it inside its first bracket pair while it is too long: the
+// elements go on their own lines, indented once more.
+fn render(pieces: &[Piece], indent: usize) -> Vec<String> {
+    let flat = flatten(pieces);
+    let width = INDENT.len() * indent + flat.chars().count();
+    if width <= MAX_WIDTH {
+        return vec![INDENT.repeat(indent) + &flat];
+    }
+
+    let Some((open, close)) = first_group(pieces) else {
+        return vec![INDENT.repeat(indent) + &flat];
+    };
+
+    let mut lines = vec![INDENT.repeat(indent) + &flatten(&pieces[..=open])];
+    let mut depth = 0;
+    let mut element_start = open + 1;
+    for index in open + 1..close {
+        match pieces[index].kind {
+            PieceKind::Open => depth += 1,
+            PieceKind::Close => depth -= 1,
+            PieceKind::Comma if depth == 0 => {
+                lines.extend(render(&pieces[element_start..=index], indent + 1));
+                element_start = index + 1;
+            }
+            _ => (),
+        }
+    }
+    if element_start < close {
+        lines.extend(render(&pieces[element_start..close], indent + 1));
+    }
+    lines.extend(render(&pieces[close..], indent));
+    lines
+}
+
+fn flatten(pieces: &[Piece]) -> String {
+    let mut line = String::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        if i > 0 && piece.space_before {
+            line.push(' ');
+        }
+        line.push_str(&piece.text);
+    }
+    line
+}
+
+// The first non-empty bracket pair which opens and closes within the line.
+fn first_group(pieces: &[Piece]) -> Option<(usize, usize)> {
+    let mut open = None;
+    let mut depth = 0;
+    for (index, piece) in pieces.iter().enumerate() {
+        match piece.kind {
+            PieceKind::Open => {
+                if depth == 0 {
+                    open = Some(index);
+                }
+                depth += 1;
+            }
+            PieceKind::Close if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let open = open.take()?;
+                    if index > open + 1 {
+                        return Some((open, index));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
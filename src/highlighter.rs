@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use crate::{
+    analysis::{Analysis, SymbolKind},
+    custom_scanner::Scanner,
+    interpreter::Interpreter,
+    take_syntax_errors,
+    token::Token,
+    token_type::TokenType,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    Keyword,
+    Literal, // true, false and nil
+    String,
+    Number,
+    Comment,
+    Operator,
+    Global,
+    Local,
+    Parameter,
+    Function,
+    Class,
+    Field, // properties and methods, after a `.`
+    Builtin,
+    Plain, // punctuation, and names when the source doesn't parse
+}
+
+impl Style {
+    // The HTML class is `lox-` followed by this.
+    pub fn name(self) -> &'static str {
+        match self {
+            Style::Keyword => "keyword",
+            Style::Literal => "literal",
+            Style::String => "string",
+            Style::Number => "number",
+            Style::Comment => "comment",
+            Style::Operator => "operator",
+            Style::Global => "global",
+            Style::Local => "local",
+            Style::Parameter => "parameter",
+            Style::Function => "function",
+            Style::Class => "class",
+            Style::Field => "field",
+            Style::Builtin => "builtin",
+            Style::Plain => "plain",
+        }
+    }
+
+    fn ansi(self) -> Option<&'static str> {
+        match self {
+            Style::Keyword => Some("35"),
+            Style::Literal | Style::Number => Some("33"),
+            Style::String => Some("32"),
+            Style::Comment => Some("90"),
+            Style::Global => Some("34"),
+            Style::Parameter => Some("3"),
+            Style::Function => Some("94"),
+            Style::Class => Some("1;33"),
+            Style::Field => Some("36"),
+            Style::Builtin => Some("96"),
+            Style::Operator | Style::Local | Style::Plain => None,
+        }
+    }
+}
+
+// Colors source code by its tokens, and names by what they refer to: globals, locals,
+// parameters, fields, functions, classes and builtins. Names are only told apart when the
+// source parses, otherwise they are plain.
+//
+//     let html = Highlighter::new().html(source);
+pub struct Highlighter {
+    builtins: Vec<String>,
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Highlighter {
+    pub fn new() -> Self {
+        Highlighter {
+            builtins: Interpreter::new().global_names(),
+        }
+    }
+
+    // The source cut into pieces which cover all of it, whitespace included.
+    pub fn highlight<'a>(&self, source: &'a str) -> Vec<(Style, &'a str)> {
+        let mut scanner = Scanner::with_comments(source);
+        let tokens = scanner.scan_tokens().to_vec();
+        // Characters the scanner rejects are left plain.
+        let _ = take_syntax_errors();
+
+        let names = self.names(source);
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        let mut pieces = Vec::new();
+        let mut position = 0;
+        for token in tokens.iter().filter(|token| token.c_type != TokenType::Eof) {
+            // Tokens spanning lines know the line they end on and the column they start at.
+            let start_line = token.line as usize - token.lexeme.matches('\n').count();
+            let line_start = line_starts[start_line - 1];
+            let start = source[line_start..]
+                .char_indices()
+                .nth(token.column as usize - 1)
+                .map_or(source.len(), |(index, _)| line_start + index);
+
+            if start > position {
+                pieces.push((Style::Plain, &source[position..start]));
+            }
+            let end = start + token.lexeme.len();
+            let style = match token.c_type {
+                TokenType::Identifier => names
+                    .get(&(token.line, token.column))
+                    .copied()
+                    .unwrap_or(Style::Plain),
+                c_type => style(c_type),
+            };
+            pieces.push((style, &source[start..end]));
+            position = end;
+        }
+        if position < source.len() {
+            pieces.push((Style::Plain, &source[position..]));
+        }
+        pieces
+    }
+
+    // A `<pre>` block with a `<span class="lox-...">` around each token which isn't plain.
+    pub fn html(&self, source: &str) -> String {
+        let mut html = String::from("<pre class=\"lox\"><code>");
+        for (style, text) in self.highlight(source) {
+            let text = escape_html(text);
+            match style {
+                Style::Plain => html.push_str(&text),
+                style => html.push_str(&format!(
+                    "<span class=\"lox-{}\">{text}</span>",
+                    style.name()
+                )),
+            }
+        }
+        html.push_str("</code></pre>\n");
+        html
+    }
+
+    // The source with ANSI color escapes, for terminals.
+    pub fn ansi(&self, source: &str) -> String {
+        let mut output = String::new();
+        for (style, text) in self.highlight(source) {
+            match style.ansi() {
+                Some(color) => output.push_str(&format!("\x1b[{color}m{text}\x1b[0m")),
+                None => output.push_str(text),
+            }
+        }
+        output
+    }
+
+    // The style of each name, by the line and column it starts at.
+    fn names(&self, source: &str) -> HashMap<(u32, u32), Style> {
+        let Ok(analysis) = Analysis::new(source) else {
+            return HashMap::new();
+        };
+
+        let mut names = HashMap::new();
+        for symbol in &analysis.symbols {
+            let style = match symbol.kind {
+                SymbolKind::Function => Style::Function,
+                SymbolKind::Class => Style::Class,
+                SymbolKind::Method => Style::Field,
+                SymbolKind::Parameter => Style::Parameter,
+                SymbolKind::Variable | SymbolKind::Import if symbol.global => Style::Global,
+                SymbolKind::Variable | SymbolKind::Import => Style::Local,
+            };
+            for span in std::iter::once(&symbol.declaration).chain(&symbol.references) {
+                names.insert((span.line, span.column), style);
+            }
+        }
+        for property in &analysis.properties {
+            names.insert(position(property), Style::Field);
+        }
+        // Names not declared in the source are defined by earlier code, like in the REPL.
+        for name in &analysis.unresolved {
+            let style = if self
+                .builtins
+                .iter()
+                .any(|builtin| **builtin == *name.lexeme)
+            {
+                Style::Builtin
+            } else {
+                Style::Global
+            };
+            names.insert(position(name), style);
+        }
+        names
+    }
+}
+
+fn position(token: &Token) -> (u32, u32) {
+    (token.line, token.column)
+}
+
+fn style(c_type: TokenType) -> Style {
+    match c_type {
+        TokenType::True | TokenType::False | TokenType::Nil => Style::Literal,
+        TokenType::String => Style::String,
+        TokenType::Number => Style::Number,
+        TokenType::Comment => Style::Comment,
+        TokenType::And
+        | TokenType::Break
+        | TokenType::Catch
+        | TokenType::Class
+        | TokenType::Else
+        | TokenType::Export
+        | TokenType::Finally
+        | TokenType::Fun
+        | TokenType::For
+        | TokenType::If
+        | TokenType::Import
+        | TokenType::In
+        | TokenType::Or
+        | TokenType::Print
+        | TokenType::Return
+        | TokenType::Super
+        | TokenType::This
+        | TokenType::Throw
+        | TokenType::Try
+        | TokenType::Var
+        | TokenType::While => Style::Keyword,
+        TokenType::Minus
+        | TokenType::Plus
+        | TokenType::Slash
+        | TokenType::Star
+        | TokenType::Bang
+        | TokenType::BangEqual
+        | TokenType::Equal
+        | TokenType::EqualEqual
+        | TokenType::Arrow
+        | TokenType::Greater
+        | TokenType::GreaterEqual
+        | TokenType::Less
+        | TokenType::LessEqual => Style::Operator,
+        _ => Style::Plain,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,1204 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs, hint,
+    io::{self, Write},
+    mem,
+    path::{Path, PathBuf},
+    rc::Rc,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    capabilities::{Capabilities, Capability},
+    compile,
+    environment::Environment,
+    expression::Expr,
+    limits::Limits,
+    lox_callable::{LoxCallable, NativeFunction},
+    lox_class::LoxClass,
+    lox_error::LoxError,
+    lox_function::LoxFunction,
+    lox_instance::LoxInstance,
+    lox_list,
+    lox_map::{self, LoxMap, MapKey},
+    lox_module::{self, display_path, LoxModule},
+    runtime_error::{RuntimeError, StackFrame, Unwind},
+    statement::{ClassStmt, ForInStmt, FunctionDecl, Stmt, TryStmt},
+    stdlib,
+    token::{Literal, Token},
+    token_type::TokenType,
+    value::Value,
+};
+
+// Lox code run by every new interpreter before the user's code.
+const PRELUDE: &str = "
+class Error {
+    init(message) {
+        this.message = message;
+        this.line = nil;
+        this.stack = [];
+    }
+}
+";
+
+// A call to a Lox function which hasn't returned yet.
+struct CallFrame {
+    function: Rc<str>,
+    call_line: u32,
+    // The scope the call was made from, where the caller continues once it returns.
+    caller_environment: Rc<RefCell<Environment>>,
+    caller_file: Option<Rc<str>>,
+}
+
+// Called before every statement runs, for debuggers. Returning an error unwinds the script
+// as if the statement had raised it.
+pub trait StatementHook {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        stmt: &Stmt,
+        line: u32,
+    ) -> Result<(), Unwind>;
+}
+
+pub struct Interpreter {
+    // Natives and the prelude, shared by every module.
+    builtins: Rc<RefCell<Environment>>,
+    // Top level scope of the module currently running.
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    // Expression id -> number of scopes between the use and the declaration.
+    // Variables not in here are globals.
+    locals: HashMap<usize, usize>,
+    frames: Vec<CallFrame>,
+    // The prelude's `Error` class, kept even if the global gets reassigned.
+    error_class: Option<Rc<LoxClass>>,
+    // Modules are evaluated once, keyed by their canonical path.
+    modules: HashMap<PathBuf, Rc<LoxModule>>,
+    // Files whose top level code is running, the last one is the innermost import.
+    loading: Vec<PathBuf>,
+    // Command line arguments after the script path, returned by `os.args()`.
+    args: Vec<String>,
+    // Where `print` writes to, stdout unless the embedder redirects it.
+    output: Box<dyn Write>,
+    // What natives touching the outside world may do, nothing unless the embedder allows it.
+    capabilities: Capabilities,
+    limits: Limits,
+    // Usage counted against the limits, reset on every `interpret`.
+    fuel_used: u64,
+    heap_used: usize,
+    deadline: Option<Instant>,
+    // Where the Rust stack was when the outermost Lox call started.
+    stack_base: usize,
+    // Last line seen while executing, for errors which don't come with a token.
+    line: u32,
+    // The file of the code running, for stack traces. None for code not read from a file.
+    file: Option<Rc<str>>,
+    hook: Option<Box<dyn StatementHook>>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let builtins = Rc::new(RefCell::new(Environment::new()));
+
+        builtins.borrow_mut().define(
+            "clock",
+            Value::Native(Rc::new(NativeFunction::new(0, |interpreter, paren, _| {
+                interpreter.capabilities().check(paren, Capability::Clock)?;
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0.0, |duration| duration.as_secs_f64());
+                Ok(Value::Number(now))
+            }))),
+        );
+
+        builtins.borrow_mut().define(
+            "range",
+            Value::Native(Rc::new(NativeFunction::new(
+                2,
+                |_, paren, arguments| match (&arguments[0], &arguments[1]) {
+                    (Value::Number(start), Value::Number(end)) => Ok(Value::Range(*start, *end)),
+                    _ => Err(RuntimeError::new(paren, "Range bounds must be numbers.").into()),
+                },
+            ))),
+        );
+
+        stdlib::define_modules(&mut builtins.borrow_mut());
+
+        let mut interpreter = Self {
+            environment: Rc::clone(&builtins),
+            globals: Rc::clone(&builtins),
+            builtins,
+            locals: HashMap::new(),
+            frames: Vec::new(),
+            error_class: None,
+            modules: HashMap::new(),
+            loading: Vec::new(),
+            args: Vec::new(),
+            output: Box::new(io::stdout()),
+            capabilities: Capabilities::none(),
+            limits: Limits::default(),
+            fuel_used: 0,
+            heap_used: 0,
+            deadline: None,
+            stack_base: 0,
+            line: 0,
+            file: None,
+            hook: None,
+        };
+        interpreter.run_prelude();
+
+        let globals = interpreter.new_module_scope();
+        interpreter.environment = Rc::clone(&globals);
+        interpreter.globals = globals;
+        interpreter
+    }
+
+    fn run_prelude(&mut self) {
+        let statements = compile(PRELUDE, self).expect("the prelude compiles");
+        if let Err(error) = self.interpret(&statements) {
+            panic!("the prelude failed to run: {error}");
+        }
+
+        let error_name = Token::new(TokenType::Identifier, "Error", None, 0);
+        if let Ok(Value::Class(error_class)) = self.globals.borrow().get(&error_name) {
+            self.error_class = Some(error_class);
+        }
+    }
+
+    // Returns the value of the last statement if it is an expression statement, nil otherwise.
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<Value, LoxError> {
+        self.fuel_used = 0;
+        self.heap_used = 0;
+        self.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut last = Value::Nil;
+        for statement in statements {
+            let result = match statement {
+                Stmt::Expression(expr) => self
+                    .before_statement(statement)
+                    .and_then(|()| self.evaluate(expr))
+                    .map(|value| last = value),
+                statement => self.execute(statement).map(|()| last = Value::Nil),
+            };
+
+            match result {
+                Ok(()) => (),
+                Err(Unwind::Error(error)) => return Err(LoxError::Runtime(error)),
+                Err(Unwind::Exit(code)) => return Err(LoxError::Exit(code)),
+                // The resolver rejects top level returns and breaks.
+                Err(Unwind::Return(_) | Unwind::Break) => break,
+            }
+        }
+        Ok(last)
+    }
+
+    // Runs the script at `path` as the main module, imports inside it resolve relative to it.
+    pub fn run_main(&mut self, path: &Path) -> Result<(), LoxError> {
+        let no_input = |error| LoxError::NoInput(path.to_path_buf(), error);
+        let source = fs::read_to_string(path).map_err(no_input)?;
+        let path = path.canonicalize().map_err(no_input)?;
+        let statements = compile(&source, self).map_err(LoxError::Compile)?;
+
+        let previous_file = self.file.replace(Rc::from(display_path(&path)));
+        self.loading.push(path);
+        let result = self.interpret(&statements);
+        self.loading.pop();
+        self.file = previous_file;
+        result.map(|_| ())
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn set_statement_hook(&mut self, hook: Option<Box<dyn StatementHook>>) {
+        self.hook = hook;
+    }
+
+    // Counts one evaluated node against the fuel, and checks the clock every so often.
+    fn tick(&mut self, line: u32) -> Result<(), RuntimeError> {
+        self.line = line;
+
+        self.fuel_used += 1;
+        if self.limits.fuel.is_some_and(|fuel| self.fuel_used > fuel) {
+            return Err(self.limit_error("Execution ran out of fuel."));
+        }
+        if self.fuel_used.is_multiple_of(1024)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(self.limit_error("Execution timed out."));
+        }
+        Ok(())
+    }
+
+    // Charges an allocation made on behalf of the script against the heap limit.
+    pub fn allocate(&mut self, bytes: usize) -> Result<(), RuntimeError> {
+        self.heap_used = self.heap_used.saturating_add(bytes);
+        if self.limits.max_heap.is_some_and(|max| self.heap_used > max) {
+            return Err(self.limit_error("Memory limit exceeded."));
+        }
+        Ok(())
+    }
+
+    // Limits aren't tied to any one token, so they are reported on the current line.
+    // Sleeps for at most the time left before the timeout, which then fires right away.
+    pub fn sleep(&mut self, duration: Duration) -> Result<(), RuntimeError> {
+        let left = self
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        match left {
+            Some(left) if left <= duration => {
+                thread::sleep(left);
+                Err(self.limit_error("Execution timed out."))
+            }
+            _ => {
+                thread::sleep(duration);
+                Ok(())
+            }
+        }
+    }
+
+    fn limit_error(&self, message: &str) -> RuntimeError {
+        let token = Token::new(TokenType::Eof, "", None, self.line);
+        RuntimeError::fatal(&token, message)
+    }
+
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    pub fn flush_output(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+
+    // Globals are defined in the top level scope of the main script.
+    pub fn define_global(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().define(name, value);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        let name = Token::new(TokenType::Identifier, name, None, 0);
+        self.globals.borrow().get(&name).ok()
+    }
+
+    // The globals defined by the code run so far, without the builtins, sorted by name.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        let mut globals: Vec<(String, Value)> = self
+            .globals
+            .borrow()
+            .variables()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        globals.sort_by(|(a, _), (b, _)| a.cmp(b));
+        globals
+    }
+
+    // Every name visible at the top level, builtins included, sorted.
+    pub fn global_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = [&self.globals, &self.builtins]
+            .iter()
+            .flat_map(|scope| {
+                let scope = scope.borrow();
+                scope
+                    .variables()
+                    .map(|(name, _)| name.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn new_module_scope(&self) -> Rc<RefCell<Environment>> {
+        Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(
+            &self.builtins,
+        ))))
+    }
+
+    pub fn replace_globals(
+        &mut self,
+        globals: Rc<RefCell<Environment>>,
+    ) -> Rc<RefCell<Environment>> {
+        std::mem::replace(&mut self.globals, globals)
+    }
+
+    fn load_module(&mut self, keyword: &Token, path: &str) -> Result<Rc<LoxModule>, Unwind> {
+        let path = lox_module::resolve_path(self.loading.last().map(PathBuf::as_path), path);
+
+        if let Some(module) = self.modules.get(&path) {
+            return Ok(Rc::clone(module));
+        }
+
+        if let Some(start) = self.loading.iter().position(|loading| *loading == path) {
+            let chain: Vec<String> = self.loading[start..]
+                .iter()
+                .chain([&path])
+                .map(|path| display_path(path))
+                .collect();
+            return Err(RuntimeError::new(
+                keyword,
+                format!("Import cycle detected: {}.", chain.join(" -> ")),
+            )
+            .into());
+        }
+
+        self.capabilities
+            .check(keyword, Capability::FsRead(&path))?;
+        let source = fs::read_to_string(&path).map_err(|error| {
+            RuntimeError::new(
+                keyword,
+                format!("Can't read module '{}': {error}.", display_path(&path)),
+            )
+        })?;
+        let statements = compile(&source, self).map_err(|errors| {
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            RuntimeError::new(
+                keyword,
+                format!(
+                    "Module '{}' has compile errors:\n{}",
+                    display_path(&path),
+                    errors.join("\n")
+                ),
+            )
+        })?;
+
+        // The module's top level code runs in its own scope, as if it were the main script.
+        let environment = self.new_module_scope();
+        let previous_globals = self.replace_globals(Rc::clone(&environment));
+        let previous_environment =
+            std::mem::replace(&mut self.environment, Rc::clone(&environment));
+        let previous_file = self.file.replace(Rc::from(display_path(&path)));
+        self.loading.push(path.clone());
+
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement));
+
+        self.loading.pop();
+        self.file = previous_file;
+        self.environment = previous_environment;
+        self.globals = previous_globals;
+        result?;
+
+        let module = Rc::new(LoxModule::new(path.clone(), environment, &statements));
+        self.modules.insert(path, Rc::clone(&module));
+        Ok(module)
+    }
+
+    fn new_function(&self, declaration: &Rc<FunctionDecl>, is_initializer: bool) -> LoxFunction {
+        LoxFunction::new(
+            Rc::clone(declaration),
+            Rc::clone(&self.environment),
+            Rc::clone(&self.globals),
+            self.file.clone(),
+            is_initializer,
+        )
+    }
+
+    // Fails instead of pushing once the call depth or stack limit is reached, before the
+    // recursion can overflow the Rust stack. `file` is where the function was declared.
+    pub fn push_frame(
+        &mut self,
+        function: Rc<str>,
+        file: Option<Rc<str>>,
+        call_line: u32,
+    ) -> Result<(), RuntimeError> {
+        let here = stack_address();
+        if self.frames.is_empty() {
+            self.stack_base = here;
+        }
+        if self.frames.len() >= self.limits.max_call_depth
+            || self.stack_base.abs_diff(here) > self.limits.max_stack
+        {
+            let token = Token::new(TokenType::Eof, "", None, call_line);
+            return Err(RuntimeError::fatal(&token, "Stack overflow."));
+        }
+
+        self.frames.push(CallFrame {
+            function,
+            call_line,
+            caller_environment: Rc::clone(&self.environment),
+            caller_file: mem::replace(&mut self.file, file),
+        });
+        Ok(())
+    }
+
+    // Pops the innermost frame, capturing the stack trace first if an error is leaving it.
+    pub fn pop_frame(&mut self, result: Result<(), Unwind>) -> Result<(), Unwind> {
+        let result = match result {
+            Err(Unwind::Error(mut error)) => {
+                if error.stack.is_none() {
+                    error.stack = Some(self.capture_stack(error.line));
+                }
+                Err(Unwind::Error(error))
+            }
+            result => result,
+        };
+        if let Some(frame) = self.frames.pop() {
+            self.file = frame.caller_file;
+        }
+        result
+    }
+
+    // The file of the running code, as shown in stack traces.
+    pub fn current_file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    // The number of Lox calls which haven't returned yet.
+    pub fn call_depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    // The frames of the running code given the line it is at, innermost first.
+    pub fn stack_trace(&self, line: u32) -> Vec<StackFrame> {
+        self.capture_stack(line)
+    }
+
+    // The scope a frame of `stack_trace` is executing in.
+    fn frame_environment(&self, frame: usize) -> Option<Rc<RefCell<Environment>>> {
+        match frame {
+            0 => Some(Rc::clone(&self.environment)),
+            frame => self
+                .frames
+                .len()
+                .checked_sub(frame)
+                .map(|index| Rc::clone(&self.frames[index].caller_environment)),
+        }
+    }
+
+    // The local variables of a frame of `stack_trace`, innermost scope first. Module globals
+    // and builtins aren't included.
+    pub fn frame_variables(&self, frame: usize) -> Vec<Vec<(String, Value)>> {
+        let mut scopes = Vec::new();
+        let mut environment = self.frame_environment(frame);
+        while let Some(scope) = environment {
+            let enclosing = scope.borrow().enclosing();
+            // The module scope is the one enclosed by the builtins, which enclose nothing.
+            if enclosing
+                .as_ref()
+                .is_none_or(|enclosing| enclosing.borrow().enclosing().is_none())
+            {
+                break;
+            }
+
+            let mut variables: Vec<(String, Value)> = scope
+                .borrow()
+                .variables()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect();
+            variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+            scopes.push(variables);
+            environment = enclosing;
+        }
+        scopes
+    }
+
+    // Evaluates code as if it were written where a frame of `stack_trace` is paused. Names
+    // are looked up through the frame's scopes at runtime, there is no resolver data for them.
+    pub fn evaluate_in_frame(&mut self, source: &str, frame: usize) -> Result<Value, LoxError> {
+        let environment = self
+            .frame_environment(frame)
+            .ok_or_else(|| LoxError::Usage(format!("There is no frame {frame}.")))?;
+        let statements = compile(source, self).map_err(LoxError::Compile)?;
+
+        let previous_globals = self.replace_globals(Rc::clone(&environment));
+        let previous_environment = mem::replace(&mut self.environment, environment);
+        let hook = self.hook.take();
+
+        let mut result = Ok(Value::Nil);
+        for statement in &statements {
+            result = match statement {
+                Stmt::Expression(expr) => self.evaluate(expr),
+                statement => self.execute(statement).map(|()| Value::Nil),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.hook = hook;
+        self.environment = previous_environment;
+        self.globals = previous_globals;
+        match result {
+            Ok(value) => Ok(value),
+            Err(Unwind::Error(error)) => Err(LoxError::Runtime(error)),
+            Err(Unwind::Exit(code)) => Err(LoxError::Exit(code)),
+            Err(Unwind::Return(_) | Unwind::Break) => Ok(Value::Nil),
+        }
+    }
+
+    // Innermost frame first, ending with the top level script.
+    fn capture_stack(&self, line: u32) -> Vec<StackFrame> {
+        let mut stack = Vec::with_capacity(self.frames.len() + 1);
+        let mut line = line;
+        let mut file = self.file.clone();
+        for frame in self.frames.iter().rev() {
+            stack.push(StackFrame {
+                function: Rc::clone(&frame.function),
+                file,
+                line,
+            });
+            line = frame.call_line;
+            file = frame.caller_file.clone();
+        }
+        stack.push(StackFrame {
+            function: Rc::from("<script>"),
+            file,
+            line,
+        });
+        stack
+    }
+
+    fn is_error_instance(&self, instance: &Rc<RefCell<LoxInstance>>) -> bool {
+        self.error_class
+            .as_ref()
+            .is_some_and(|error_class| instance.borrow().class.is_subclass_of(error_class))
+    }
+
+    // The value a `catch` clause binds: what was thrown, or an `Error` for the interpreter's
+    // own runtime errors.
+    fn error_value(&self, error: RuntimeError) -> Value {
+        if let Some(value) = error.value {
+            return value;
+        }
+
+        let Some(error_class) = &self.error_class else {
+            return Value::Str(error.message.into());
+        };
+
+        let stack = error
+            .stack
+            .unwrap_or_else(|| self.capture_stack(error.line));
+
+        let mut instance = LoxInstance::new(Rc::clone(error_class));
+        instance.set("message", Value::Str(error.message.into()));
+        instance.set("line", Value::Number(error.line as f64));
+        instance.set("stack", stack_value(&stack));
+        Value::Instance(Rc::new(RefCell::new(instance)))
+    }
+
+    // Called by the resolver for every local variable reference.
+    pub fn resolve(&mut self, id: usize, depth: usize) {
+        self.locals.insert(id, depth);
+    }
+
+    // Counts the statement against the limits and runs the hook on it.
+    fn before_statement(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        self.tick(stmt.line())?;
+        // The statements of a block get the hook themselves.
+        if !matches!(stmt, Stmt::Block(_)) {
+            if let Some(mut hook) = self.hook.take() {
+                let result = hook.before_statement(self, stmt, self.line);
+                self.hook = Some(hook);
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        self.before_statement(stmt)?;
+        match stmt {
+            Stmt::Block(block) => self.execute_block(&block.statements, self.new_scope()),
+            Stmt::Break(_) => Err(Unwind::Break),
+            Stmt::Class(class) => self.execute_class(class),
+            Stmt::Expression(expr) => {
+                self.evaluate(expr)?;
+                Ok(())
+            }
+            Stmt::ForIn(for_in) => match self.execute_for_in(for_in) {
+                Err(Unwind::Break) => Ok(()),
+                result => result,
+            },
+            Stmt::Export(declaration) => self.execute(declaration),
+            Stmt::Function(declaration) => {
+                let function = self.new_function(declaration, false);
+                let name = declaration
+                    .name
+                    .as_ref()
+                    .map_or("", |name| name.lexeme.as_ref());
+                self.environment
+                    .borrow_mut()
+                    .define(name, Value::Function(Rc::new(function)));
+                Ok(())
+            }
+            Stmt::If(if_stmt) => {
+                if self.evaluate(&if_stmt.condition)?.is_truthy() {
+                    self.execute(&if_stmt.then_branch)
+                } else if let Some(else_branch) = &if_stmt.else_branch {
+                    self.execute(else_branch)
+                } else {
+                    Ok(())
+                }
+            }
+            Stmt::Import(import) => {
+                let Some(Literal::Str(path)) = &import.path.literal else {
+                    unreachable!("Parser only accepts string literals as module paths");
+                };
+                let module = self.load_module(&import.keyword, path)?;
+
+                let mut environment = self.environment.borrow_mut();
+                for name in &import.names {
+                    environment.define(&name.lexeme, module.get(name)?);
+                }
+                if let Some(alias) = &import.alias {
+                    environment.define(&alias.lexeme, Value::Module(module));
+                }
+                Ok(())
+            }
+            Stmt::Print(print) => {
+                let value = self.evaluate(&print.value)?;
+                writeln!(self.output, "{value}").map_err(|error| {
+                    RuntimeError::new(&print.keyword, format!("Can't print: {error}."))
+                })?;
+                Ok(())
+            }
+            Stmt::Return(return_stmt) => {
+                let value = match &return_stmt.value {
+                    Some(value) => self.evaluate(value)?,
+                    None => Value::Nil,
+                };
+                Err(Unwind::Return(value))
+            }
+            Stmt::Throw(throw) => {
+                let value = self.evaluate(&throw.value)?;
+                let stack = self.capture_stack(throw.keyword.line);
+
+                let message = match &value {
+                    Value::Instance(instance) if self.is_error_instance(instance) => {
+                        let mut instance = instance.borrow_mut();
+                        instance.set("line", Value::Number(throw.keyword.line as f64));
+                        instance.set("stack", stack_value(&stack));
+                        instance
+                            .get_field("message")
+                            .map_or(String::from("nil"), |message| message.to_string())
+                    }
+                    value => value.to_string(),
+                };
+
+                let mut error = RuntimeError::thrown(&throw.keyword, message, value);
+                error.stack = Some(stack);
+                Err(error.into())
+            }
+            Stmt::Try(try_stmt) => self.execute_try(try_stmt),
+            Stmt::Var(var_stmt) => {
+                let value = match &var_stmt.initializer {
+                    Some(initializer) => self.evaluate(initializer)?,
+                    None => Value::Nil,
+                };
+                self.environment
+                    .borrow_mut()
+                    .define(&var_stmt.name.lexeme, value);
+                Ok(())
+            }
+            Stmt::While(while_stmt) => {
+                while self.evaluate(&while_stmt.condition)?.is_truthy() {
+                    match self.execute(&while_stmt.body) {
+                        Err(Unwind::Break) => break,
+                        result => result?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Executes the statements in the given environment, restoring the current one afterwards
+    // even if a statement errors or returns.
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement));
+
+        self.environment = previous;
+        result
+    }
+
+    fn execute_try(&mut self, try_stmt: &TryStmt) -> Result<(), Unwind> {
+        let result = self.execute_block(&try_stmt.body, self.new_scope());
+
+        // Only errors are caught, returns and breaks pass through to the finally block.
+        let result = match (result, &try_stmt.catch) {
+            (Err(Unwind::Error(error)), Some(catch)) if !error.fatal => {
+                let mut environment = Environment::new_enclosed(Rc::clone(&self.environment));
+                environment.define(&catch.name.lexeme, self.error_value(error));
+                self.execute_block(&catch.body, Rc::new(RefCell::new(environment)))
+            }
+            (result, _) => result,
+        };
+
+        // The finally block always runs. If it unwinds itself, that replaces the earlier outcome.
+        if let Some(finally) = &try_stmt.finally {
+            self.execute_block(finally, self.new_scope())?;
+        }
+
+        result
+    }
+
+    fn new_scope(&self) -> Rc<RefCell<Environment>> {
+        Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(
+            &self.environment,
+        ))))
+    }
+
+    fn execute_class(&mut self, class: &ClassStmt) -> Result<(), Unwind> {
+        let superclass = match &class.superclass {
+            Some(superclass_expr) => match self.evaluate(superclass_expr)? {
+                // The Rust struct behind a native class can't hold the fields of a subclass.
+                Value::Class(superclass) if superclass.native.is_some() => {
+                    return Err(RuntimeError::new(
+                        &class.name,
+                        format!("Can't inherit from native class '{}'.", superclass.name),
+                    )
+                    .into());
+                }
+                Value::Class(superclass) => Some(superclass),
+                _ => {
+                    let name = match superclass_expr {
+                        Expr::Variable(variable) => &variable.name,
+                        _ => &class.name,
+                    };
+                    return Err(RuntimeError::new(name, "Superclass must be a class.").into());
+                }
+            },
+            None => None,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(&class.name.lexeme, Value::Nil);
+
+        // Methods of a subclass close over an environment holding `super`.
+        let previous = Rc::clone(&self.environment);
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new_enclosed(Rc::clone(&self.environment));
+            environment.define("super", Value::Class(Rc::clone(superclass)));
+            self.environment = Rc::new(RefCell::new(environment));
+        }
+
+        let methods = class
+            .methods
+            .iter()
+            .map(|method| {
+                let name = method
+                    .name
+                    .as_ref()
+                    .map_or(String::new(), |name| name.lexeme.to_string());
+                let function = self.new_function(method, name == "init");
+                (name, Rc::new(function))
+            })
+            .collect();
+
+        let class_value = LoxClass::new(Rc::clone(&class.name.lexeme), superclass, methods);
+
+        self.environment = previous;
+        self.environment
+            .borrow_mut()
+            .assign(&class.name, Value::Class(Rc::new(class_value)))?;
+        Ok(())
+    }
+
+    // Built-in collections are iterated over a snapshot, so the body may mutate them freely.
+    // Instances follow the iterator protocol: `iter()` returns an object with a `next()` method
+    // and a `done` field or zero argument method.
+    fn execute_for_in(&mut self, for_in: &ForInStmt) -> Result<(), Unwind> {
+        match self.evaluate(&for_in.iterable)? {
+            Value::List(list) => {
+                let elements = list.borrow().clone();
+                for element in elements {
+                    self.execute_for_in_body(for_in, element)?;
+                }
+            }
+            Value::Map(map) => {
+                let keys = map.borrow().keys();
+                for key in keys {
+                    self.execute_for_in_body(for_in, key)?;
+                }
+            }
+            Value::Str(s) => {
+                for ch in s.chars() {
+                    self.execute_for_in_body(for_in, Value::Str(ch.to_string().into()))?;
+                }
+            }
+            Value::Range(start, end) => {
+                let mut current = start;
+                while current < end {
+                    self.execute_for_in_body(for_in, Value::Number(current))?;
+                    current += 1.0;
+                }
+            }
+            Value::Instance(instance) => {
+                let property =
+                    |name: &str| Token::new(TokenType::Identifier, name, None, for_in.keyword.line);
+
+                let iter = LoxInstance::get(&instance, &property("iter"))?;
+                let Value::Instance(iterator) = self.call(&iter, &for_in.keyword, Vec::new())?
+                else {
+                    return Err(RuntimeError::new(
+                        &for_in.keyword,
+                        "'iter' must return an iterator instance.",
+                    )
+                    .into());
+                };
+
+                loop {
+                    let done = match LoxInstance::get(&iterator, &property("done"))? {
+                        done @ (Value::Function(_) | Value::Native(_)) => {
+                            self.call(&done, &for_in.keyword, Vec::new())?
+                        }
+                        done => done,
+                    };
+                    if done.is_truthy() {
+                        break;
+                    }
+
+                    let next = LoxInstance::get(&iterator, &property("next"))?;
+                    let element = self.call(&next, &for_in.keyword, Vec::new())?;
+                    self.execute_for_in_body(for_in, element)?;
+                }
+            }
+            _ => {
+                return Err(RuntimeError::new(
+                    &for_in.keyword,
+                    "Can only iterate over lists, maps, strings, ranges and iterators.",
+                )
+                .into())
+            }
+        }
+
+        Ok(())
+    }
+
+    // Every iteration gets a fresh environment, so closures capture that iteration's element.
+    fn execute_for_in_body(&mut self, for_in: &ForInStmt, element: Value) -> Result<(), Unwind> {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.environment));
+        environment.define(&for_in.name.lexeme, element);
+        self.execute_block(
+            std::slice::from_ref(&for_in.body),
+            Rc::new(RefCell::new(environment)),
+        )
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value, Unwind> {
+        self.tick(expr.line())?;
+        match expr {
+            Expr::Assign(assign) => {
+                let value = self.evaluate(&assign.value)?;
+                match self.locals.get(&assign.id) {
+                    Some(&distance) => Environment::assign_at(
+                        &self.environment,
+                        distance,
+                        &assign.name,
+                        value.clone(),
+                    ),
+                    None => self
+                        .globals
+                        .borrow_mut()
+                        .assign(&assign.name, value.clone())?,
+                }
+                Ok(value)
+            }
+            Expr::Binary(binary) => {
+                let left = self.evaluate(&binary.left)?;
+                let right = self.evaluate(&binary.right)?;
+                self.binary(&binary.operator, left, right)
+            }
+            Expr::Call(call) => {
+                let callee = self.evaluate(&call.callee)?;
+
+                let mut arguments = Vec::with_capacity(call.arguments.len());
+                for argument in &call.arguments {
+                    arguments.push(self.evaluate(argument)?);
+                }
+
+                self.call(&callee, &call.paren, arguments)
+            }
+            Expr::Get(get) => match self.evaluate(&get.object)? {
+                Value::Instance(instance) => Ok(LoxInstance::get(&instance, &get.name)?),
+                Value::List(list) => Ok(lox_list::get_method(&list, &get.name)?),
+                Value::Map(map) => Ok(lox_map::get_method(&map, &get.name)?),
+                Value::Module(module) => Ok(module.get(&get.name)?),
+                _ => Err(RuntimeError::new(&get.name, "Only instances have properties.").into()),
+            },
+            Expr::Grouping(expr) => self.evaluate(expr),
+            Expr::Index(index) => {
+                let object = self.evaluate(&index.object)?;
+                let key = self.evaluate(&index.index)?;
+                match object {
+                    Value::List(list) => Ok(lox_list::get(&list, &index.bracket, &key)
+                        .map_err(|error| error.with_span(index.span))?),
+                    Value::Map(map) => lox_map::get(self, &map, &index.bracket, &key),
+                    _ => Err(RuntimeError::new(
+                        &index.bracket,
+                        "Only lists and maps can be indexed.",
+                    )
+                    .with_span(index.span)
+                    .into()),
+                }
+            }
+            Expr::Lambda(declaration) => {
+                let function = self.new_function(declaration, false);
+                Ok(Value::Function(Rc::new(function)))
+            }
+            Expr::List(list) => {
+                let mut elements = Vec::with_capacity(list.elements.len());
+                for element in &list.elements {
+                    elements.push(self.evaluate(element)?);
+                }
+                self.allocate(elements.len() * mem::size_of::<Value>())?;
+                Ok(lox_list::new_list(elements))
+            }
+            Expr::Literal(literal) => Ok(literal.value.clone().into()),
+            Expr::Logical(logical) => {
+                let left = self.evaluate(&logical.left)?;
+
+                // Short circuit, the result is the operand which decided the outcome.
+                if logical.operator.c_type == TokenType::Or {
+                    if left.is_truthy() {
+                        return Ok(left);
+                    }
+                } else if !left.is_truthy() {
+                    return Ok(left);
+                }
+
+                self.evaluate(&logical.right)
+            }
+            Expr::Map(map_expr) => {
+                let mut map = LoxMap::new();
+                for (key, value) in &map_expr.entries {
+                    let key = self.evaluate(key)?;
+                    let value = self.evaluate(value)?;
+                    let hashed = MapKey::new(self, &map_expr.brace, &key)?;
+                    map.insert(hashed, key, value);
+                }
+                self.allocate(map.len() * lox_map::ENTRY_SIZE)?;
+                Ok(lox_map::new_map(map))
+            }
+            Expr::Set(set) => {
+                let Value::Instance(instance) = self.evaluate(&set.object)? else {
+                    return Err(RuntimeError::new(&set.name, "Only instances have fields.").into());
+                };
+
+                let value = self.evaluate(&set.value)?;
+                LoxInstance::set_property(&instance, &set.name, value.clone())?;
+                Ok(value)
+            }
+            Expr::SetIndex(set_index) => {
+                let object = self.evaluate(&set_index.object)?;
+                let key = self.evaluate(&set_index.index)?;
+                let value = self.evaluate(&set_index.value)?;
+                match object {
+                    Value::List(list) => {
+                        lox_list::set(&list, &set_index.bracket, &key, value.clone())
+                            .map_err(|error| error.with_span(set_index.span))?
+                    }
+                    Value::Map(map) => {
+                        lox_map::set(self, &map, &set_index.bracket, key, value.clone())?
+                    }
+                    _ => {
+                        return Err(RuntimeError::new(
+                            &set_index.bracket,
+                            "Only lists and maps can be indexed.",
+                        )
+                        .with_span(set_index.span)
+                        .into())
+                    }
+                }
+                Ok(value)
+            }
+            Expr::Super(super_expr) => {
+                let distance = self.locals.get(&super_expr.id).copied().unwrap_or(0);
+                let Value::Class(superclass) =
+                    Environment::get_at(&self.environment, distance, "super")
+                else {
+                    return Err(RuntimeError::new(
+                        &super_expr.keyword,
+                        "Superclass must be a class.",
+                    )
+                    .into());
+                };
+
+                // `this` always lives in the environment right inside the one holding `super`.
+                let Value::Instance(instance) =
+                    Environment::get_at(&self.environment, distance.saturating_sub(1), "this")
+                else {
+                    return Err(RuntimeError::new(
+                        &super_expr.keyword,
+                        "Can't use 'super' outside of a class.",
+                    )
+                    .into());
+                };
+
+                match superclass.find_method(&super_expr.method.lexeme) {
+                    Some(method) => Ok(Value::Function(Rc::new(method.bind(instance)))),
+                    None => Err(RuntimeError::new(
+                        &super_expr.method,
+                        format!("Undefined property '{}'.", super_expr.method.lexeme),
+                    )
+                    .into()),
+                }
+            }
+            Expr::This(this) => Ok(self.look_up_variable(&this.keyword, this.id)?),
+            Expr::Unary(unary) => {
+                let right = self.evaluate(&unary.right)?;
+
+                match unary.operator.c_type {
+                    TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
+                    TokenType::Minus => {
+                        let n = self.number_operand(&unary.operator, &right)?;
+                        Ok(Value::Number(-n))
+                    }
+                    _ => unreachable!("Parser only produces '!' and '-' unary operators"),
+                }
+            }
+            Expr::Variable(variable) => Ok(self.look_up_variable(&variable.name, variable.id)?),
+        }
+    }
+
+    // Calls any callable value, checking the number of arguments first.
+    pub fn call(
+        &mut self,
+        callee: &Value,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Unwind> {
+        let callable: &dyn LoxCallable = match callee {
+            Value::Function(function) => function.as_ref(),
+            Value::Native(native) => native.as_ref(),
+            Value::Class(class) => class,
+            _ => {
+                return Err(RuntimeError::new(paren, "Can only call functions and classes.").into())
+            }
+        };
+
+        if arguments.len() != callable.arity() {
+            return Err(RuntimeError::new(
+                paren,
+                format!(
+                    "Expected {} arguments but got {}.",
+                    callable.arity(),
+                    arguments.len()
+                ),
+            )
+            .into());
+        }
+
+        callable.call(self, paren, arguments)
+    }
+
+    fn binary(&mut self, operator: &Token, left: Value, right: Value) -> Result<Value, Unwind> {
+        let value = match operator.c_type {
+            TokenType::BangEqual => Value::Bool(!left.is_equal(&right)),
+            TokenType::EqualEqual => Value::Bool(left.is_equal(&right)),
+            TokenType::Plus => match (&left, &right) {
+                (Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+                (Value::Str(a), Value::Str(b)) => {
+                    self.allocate(a.len() + b.len())?;
+                    Value::Str(format!("{a}{b}").into())
+                }
+                _ => {
+                    return Err(RuntimeError::new(
+                        operator,
+                        "Operands must be two numbers or two strings.",
+                    )
+                    .into())
+                }
+            },
+            _ => {
+                let (a, b) = self.number_operands(operator, &left, &right)?;
+                match operator.c_type {
+                    TokenType::Greater => Value::Bool(a > b),
+                    TokenType::GreaterEqual => Value::Bool(a >= b),
+                    TokenType::Less => Value::Bool(a < b),
+                    TokenType::LessEqual => Value::Bool(a <= b),
+                    TokenType::Minus => Value::Number(a - b),
+                    TokenType::Slash => Value::Number(a / b),
+                    TokenType::Star => Value::Number(a * b),
+                    _ => unreachable!("Parser only produces known binary operators"),
+                }
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn number_operand(&self, operator: &Token, operand: &Value) -> Result<f64, RuntimeError> {
+        match operand {
+            Value::Number(n) => Ok(*n),
+            _ => Err(RuntimeError::new(operator, "Operand must be a number.")),
+        }
+    }
+
+    fn number_operands(
+        &self,
+        operator: &Token,
+        left: &Value,
+        right: &Value,
+    ) -> Result<(f64, f64), RuntimeError> {
+        match (left, right) {
+            (Value::Number(a), Value::Number(b)) => Ok((*a, *b)),
+            _ => Err(RuntimeError::new(operator, "Operands must be numbers.")),
+        }
+    }
+
+    fn look_up_variable(&self, name: &Token, id: usize) -> Result<Value, RuntimeError> {
+        match self.locals.get(&id) {
+            Some(&distance) => Ok(Environment::get_at(
+                &self.environment,
+                distance,
+                &name.lexeme,
+            )),
+            None => self.globals.borrow().get(name),
+        }
+    }
+}
+
+fn stack_value(stack: &[StackFrame]) -> Value {
+    let frames = stack
+        .iter()
+        .map(|frame| Value::Str(frame.to_string().into()))
+        .collect();
+    lox_list::new_list(frames)
+}
+
+// How deep the Rust stack is, from the address of a local. The stack grows down on every
+// platform we run on, but only the distance between two calls matters.
+fn stack_address() -> usize {
+    let marker = 0u8;
+    hint::black_box(&marker) as *const u8 as usize
+}
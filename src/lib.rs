@@ -1,84 +1,429 @@
+pub mod analysis;
 pub mod ast_printer;
+pub mod capabilities;
 pub mod custom_scanner;
+pub mod dap;
+mod debugger;
+pub mod embed;
+pub mod environment;
 pub mod expression;
+pub mod formatter;
+pub mod highlighter;
+pub mod interpreter;
+pub mod limits;
+pub mod linter;
+pub mod lox_callable;
+pub mod lox_class;
+pub mod lox_convert;
+pub mod lox_error;
+pub mod lox_function;
+pub mod lox_instance;
+pub mod lox_list;
+pub mod lox_map;
+pub mod lox_module;
+pub mod lsp;
+pub mod native_class;
 pub mod parser;
+pub mod repl;
+pub mod resolver;
+pub mod runtime_error;
+pub mod statement;
+pub mod stdlib;
 pub mod token;
 pub mod token_type;
+pub mod value;
 
+pub use embed::{CapturedOutput, Lox, LoxBuilder};
+pub use lox_convert::{FromLox, FromLoxError, IntoLox};
+pub use lox_error::{LoxError, SyntaxError};
+pub use native_class::ClassBuilder;
+pub use value::Value;
+
+use ast_printer::AstPrinter;
+use capabilities::Capabilities;
 use custom_scanner::Scanner;
+use debugger::Debugger;
+use highlighter::Highlighter;
+use interpreter::Interpreter;
+use limits::Limits;
+use linter::Linter;
+use lox_module::display_path;
+use parser::Parser;
+use repl::run_prompt;
+use resolver::Resolver;
+use statement::Stmt;
 use std::{
+    cell::RefCell,
     fs,
     io::{self, Write},
-    process,
+    mem,
+    path::PathBuf,
 };
+use token::Token;
+use token_type::TokenType;
 
-static mut HAD_ERROR: bool = false;
-fn toggle_had_error(value: bool) {
-    // SAFETY: There are no other threads which could be accessing `HAD_ERROR`.
-    unsafe {
-        HAD_ERROR = value;
-    }
-}
-
-fn had_error() -> bool {
-    // SAFETY: There are no other threads which could be accessing `HAD_ERROR`.
-    unsafe { HAD_ERROR }
+thread_local! {
+    // Errors reported by the scanner, parser and resolver of the current `compile`.
+    static SYNTAX_ERRORS: RefCell<Vec<SyntaxError>> = const { RefCell::new(Vec::new()) };
 }
 
-pub fn lox<T>(mut args: T) -> Result<(), io::Error>
+// The command line driver. Errors are returned rather than exiting, the binary picks the
+// exit code.
+pub fn lox<T>(mut args: T) -> Result<(), LoxError>
 where
-    T: ExactSizeIterator<Item = String>,
+    T: Iterator<Item = String>,
 {
-    if args.len() > 2 {
-        eprintln!("Usage: jlox [script]");
-        process::exit(64);
+    args.next();
+
+    let Some(command) = args.next() else {
+        return run_prompt();
+    };
+
+    // Everything after the script is passed on to it, see `os.args()`.
+    match command.as_str() {
+        "-h" | "--help" => {
+            print!("{}", usage());
+            Ok(())
+        }
+        "-e" => {
+            let code = args
+                .next()
+                .ok_or_else(|| usage_error("Option '-e' expects the code to run."))?;
+            run_source(&code, args.collect())
+        }
+        "run" => {
+            let path = script_path(&command, args.next())?;
+            run(&path, args.collect())
+        }
+        "debug" => {
+            let path = script_path(&command, args.next())?;
+            debug(&path, args.collect())
+        }
+        "repl" => {
+            no_more_args(&command, args)?;
+            run_prompt()
+        }
+        "tokens" | "ast" | "check" => {
+            let path = script_path(&command, args.next())?;
+            no_more_args(&command, args)?;
+            let source = read_source(&path)?;
+            match command.as_str() {
+                "tokens" => print_tokens(&source, &mut io::stdout().lock()),
+                "ast" => print_ast(&source, &mut io::stdout().lock()),
+                _ => check(&source),
+            }
+        }
+        "fmt" => fmt(args.collect()),
+        "lint" => lint(args.collect()),
+        "highlight" => {
+            let mut path = script_path(&command, args.next())?;
+            let html = path == "--html";
+            if html {
+                path = script_path(&command, args.next())?;
+            }
+            no_more_args(&command, args)?;
+            highlight(&read_source(&path)?, html)
+        }
+        "dap" => {
+            no_more_args(&command, args)?;
+            dap::serve(io::stdin().lock(), io::stdout())
+        }
+        "lsp" => {
+            no_more_args(&command, args)?;
+            lsp::serve(&mut io::stdin().lock(), &mut io::stdout().lock())
+        }
+        "-" => run(&command, args.collect()),
+        option if option.starts_with('-') => {
+            Err(usage_error(&format!("Unknown option '{option}'.")))
+        }
+        // `lox script.lox` is short for `lox run script.lox`.
+        _ => run(&command, args.collect()),
     }
+}
 
-    args.next();
+const USAGE: &str = "\
+Usage:
+  lox [run] <script> [args...]  Run a script, passing it the arguments
+  lox [run] - [args...]         Run a script read from stdin
+  lox -e <code> [args...]       Run the code given on the command line
+  lox [repl]                    Start an interactive session
+  lox debug <script> [args...]  Run a script in the step debugger, type help once
+                                it pauses for its commands
+  lox tokens <script>           Print the tokens of a script
+  lox ast <script>              Print the syntax tree of a script
+  lox check <script>            Report errors in a script without running it
+  lox fmt [--check] <script>... Format scripts in place, or with --check list the
+                                ones which aren't formatted and exit with 1
+  lox lint [--json] [--allow <rule>]... <script>...
+                                Warn about likely mistakes and exit with 1 if there
+                                are any, see the rules below
+  lox highlight [--html] <script>
+                                Print a script with ANSI colors, or as HTML
+  lox lsp                       Start a language server on stdin and stdout
+  lox dap                       Start a debug adapter on stdin and stdout
+  lox -h, --help                Print this help
+
+Anywhere a script is expected, - reads it from stdin.
+
+Lint rules, silenced for a line by a `// lox-ignore: <rule>, ...` comment on it or
+on the line above:
+{RULES}
+Exit codes:
+  0   success
+  64  the command line was malformed
+  65  the script has syntax or resolution errors
+  66  the script couldn't be read
+  70  the script raised an uncaught runtime error
+  74  an I/O error occurred
+  The script can also pick its own with os.exit(code).
+";
 
+fn usage() -> String {
+    let rules: String = linter::RULES
+        .iter()
+        .map(|(rule, description)| format!("  {rule:<25}{description}\n"))
+        .collect();
+    USAGE.replace("{RULES}", &rules)
+}
+
+fn usage_error(message: &str) -> LoxError {
+    LoxError::Usage(format!("{message}\nRun 'lox --help' for usage."))
+}
+
+fn script_path(command: &str, path: Option<String>) -> Result<String, LoxError> {
+    path.ok_or_else(|| usage_error(&format!("Command '{command}' expects a script.")))
+}
+
+fn no_more_args(command: &str, mut args: impl Iterator<Item = String>) -> Result<(), LoxError> {
     match args.next() {
-        Some(file_path) => run_file(file_path)?,
-        None => run_prompt()?,
+        Some(arg) => Err(usage_error(&format!(
+            "Unexpected argument '{arg}' for command '{command}'."
+        ))),
+        None => Ok(()),
+    }
+}
+
+// `-` is stdin, anything else a file.
+fn read_source(path: &str) -> Result<String, LoxError> {
+    if path == "-" {
+        return Ok(io::read_to_string(io::stdin())?);
+    }
+    fs::read_to_string(path).map_err(|error| LoxError::NoInput(PathBuf::from(path), error))
+}
+
+// A script file is loaded as the main module, the modules it imports are loaded relative to
+// it. Scripts from stdin import relative to the working directory.
+fn run(path: &str, script_args: Vec<String>) -> Result<(), LoxError> {
+    if path == "-" {
+        let source = read_source(path)?;
+        return run_source(&source, script_args);
+    }
+    let mut lox = cli_builder().args(script_args).build();
+    lox.run_file(path)
+}
+
+// The debugger reads its commands from stdin, so the script has to come from a file.
+fn debug(path: &str, script_args: Vec<String>) -> Result<(), LoxError> {
+    if path == "-" {
+        return Err(usage_error(
+            "Command 'debug' can't read the script from stdin.",
+        ));
+    }
+    let source = read_source(path)?;
+    let file =
+        fs::canonicalize(path).map_err(|error| LoxError::NoInput(PathBuf::from(path), error))?;
+    let mut lox = cli_builder().args(script_args).build();
+    lox.set_statement_hook(Debugger::new(&display_path(&file), &source));
+    lox.run_file(path)?;
+    println!("The script finished.");
+    Ok(())
+}
+
+fn run_source(source: &str, script_args: Vec<String>) -> Result<(), LoxError> {
+    let mut lox = cli_builder().args(script_args).build();
+    lox.eval(source).map(|_| ())
+}
+
+fn print_tokens(source: &str, out: &mut impl Write) -> Result<(), LoxError> {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens().to_vec();
+    take_syntax_errors().map_err(LoxError::Compile)?;
+
+    for token in tokens {
+        writeln!(out, "{:>4}:{:<3} {token}", token.line, token.column)?;
     }
+    Ok(())
+}
 
+fn print_ast(source: &str, out: &mut impl Write) -> Result<(), LoxError> {
+    let statements = parse(source).map_err(LoxError::Compile)?;
+    write!(out, "{}", AstPrinter.print_program(&statements))?;
     Ok(())
 }
 
-fn run_file(file_path: String) -> Result<(), io::Error> {
-    let data = fs::read_to_string(file_path)?;
-    run(data);
+// HTML spans have classes like `lox-keyword`, see `highlighter::Style::name`.
+fn highlight(source: &str, html: bool) -> Result<(), LoxError> {
+    let highlighter = Highlighter::new();
+    if html {
+        print!("{}", highlighter.html(source));
+    } else {
+        print!("{}", highlighter.ansi(source));
+    }
+    Ok(())
+}
 
-    if had_error() {
-        process::exit(65);
+// Scripts read from stdin are formatted to stdout.
+fn fmt(args: Vec<String>) -> Result<(), LoxError> {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if paths.is_empty() {
+        return Err(usage_error("Command 'fmt' expects a script."));
+    }
+
+    let mut unformatted = false;
+    for path in paths {
+        let source = read_source(path)?;
+        let formatted = formatter::format(&source).map_err(LoxError::Compile)?;
+        if check {
+            if formatted != source {
+                eprintln!("{path} is not formatted.");
+                unformatted = true;
+            }
+        } else if path == "-" {
+            ignore_broken_pipe(io::stdout().lock().write_all(formatted.as_bytes()))?;
+        } else if formatted != source {
+            fs::write(path, formatted)?;
+        }
+    }
+
+    if unformatted {
+        return Err(LoxError::Exit(1));
     }
     Ok(())
 }
 
-fn run_prompt() -> Result<(), io::Error> {
-    loop {
-        print!("> ");
-        io::stdout().flush()?;
+fn lint(args: Vec<String>) -> Result<(), LoxError> {
+    let mut linter = Linter::new();
+    let mut json = false;
+    let mut paths = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--allow" => {
+                let rule = args
+                    .next()
+                    .ok_or_else(|| usage_error("Option '--allow' expects a rule."))?;
+                if !linter::RULES.iter().any(|(name, _)| *name == rule) {
+                    return Err(usage_error(&format!("Unknown lint rule '{rule}'.")));
+                }
+                linter = linter.allow(&rule);
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        return Err(usage_error("Command 'lint' expects a script."));
+    }
 
-        let mut line = String::new();
-        let bytes_read = io::stdin().read_line(&mut line)?;
+    let mut found = Vec::new();
+    for path in &paths {
+        let source = read_source(path)?;
+        let diagnostics = linter.lint(&source).map_err(LoxError::Compile)?;
+        found.extend(diagnostics.into_iter().map(|diagnostic| (path, diagnostic)));
+    }
 
-        // If EOF, break out of loop
-        if bytes_read == 0 {
-            break;
+    if json {
+        let diagnostics: Vec<serde_json::Value> = found
+            .iter()
+            .map(|(path, diagnostic)| {
+                serde_json::json!({
+                    "file": path,
+                    "rule": diagnostic.rule,
+                    "message": diagnostic.message,
+                    "line": diagnostic.span.line,
+                    "column": diagnostic.span.column,
+                    "endColumn": diagnostic.span.end_column,
+                })
+            })
+            .collect();
+        let mut stdout = io::stdout().lock();
+        ignore_broken_pipe(writeln!(
+            stdout,
+            "{}",
+            serde_json::Value::Array(diagnostics)
+        ))?;
+    } else {
+        let mut stdout = io::stdout().lock();
+        for (path, diagnostic) in &found {
+            ignore_broken_pipe(writeln!(stdout, "{path}:{diagnostic}"))?;
         }
-        run(line.trim_end().to_string());
-        toggle_had_error(false);
     }
 
+    if !found.is_empty() {
+        return Err(LoxError::Exit(1));
+    }
     Ok(())
 }
 
-fn run(line: String) {
-    let mut scanner = Scanner::new(&line);
-    let tokens = scanner.scan_tokens();
+// Output piped into something like `head` can be cut short, which isn't an error.
+fn ignore_broken_pipe(result: io::Result<()>) -> Result<(), LoxError> {
+    match result {
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
 
-    for token in tokens {
-        println!("{token:?}");
+fn check(source: &str) -> Result<(), LoxError> {
+    compile(source, &mut Interpreter::new()).map_err(LoxError::Compile)?;
+    Ok(())
+}
+
+// The tree walker recurses on the Rust stack for every Lox call, so main runs the command
+// line on a thread this big to reach the call depth limit even in debug builds.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+// Scripts run from the command line are trusted with full access, unlike embedded ones.
+fn cli_builder() -> LoxBuilder {
+    Lox::builder()
+        .capabilities(Capabilities::all())
+        .limits(Limits::new().max_stack(STACK_SIZE / 2))
+}
+
+// Scans, parses and resolves the source, returning every error found on the way.
+pub fn compile(source: &str, interpreter: &mut Interpreter) -> Result<Vec<Stmt>, Vec<SyntaxError>> {
+    let statements = parse(source)?;
+
+    let mut resolver = Resolver::new(interpreter);
+    resolver.resolve(&statements);
+
+    // Stop if there was a resolution error.
+    take_syntax_errors()?;
+
+    Ok(statements)
+}
+
+// Scans and parses the source without resolving it.
+pub fn parse(source: &str) -> Result<Vec<Stmt>, Vec<SyntaxError>> {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens().to_vec();
+
+    let mut parser = Parser::new(tokens);
+    let statements = parser.parse();
+
+    // Stop if there was a syntax error.
+    take_syntax_errors()?;
+
+    Ok(statements)
+}
+
+fn take_syntax_errors() -> Result<(), Vec<SyntaxError>> {
+    let errors = SYNTAX_ERRORS.with(|errors| mem::take(&mut *errors.borrow_mut()));
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
     }
 }
 
@@ -86,7 +431,19 @@ pub fn lox_error(line: u32, message: &str) {
     report(line, String::from(""), message);
 }
 
-fn report(line: u32, c_where: String, message: &str) {
-    eprintln!("[line {line}] Error{c_where}: {message}");
-    toggle_had_error(true);
+pub fn token_error(token: &Token, message: &str) {
+    if token.c_type == TokenType::Eof {
+        report(token.line, String::from(" at end"), message);
+    } else {
+        report(token.line, format!(" at '{}'", token.lexeme), message);
+    }
+}
+
+fn report(line: u32, location: String, message: &str) {
+    let error = SyntaxError {
+        line,
+        location,
+        message: message.to_string(),
+    };
+    SYNTAX_ERRORS.with(|errors| errors.borrow_mut().push(error));
 }
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+// Deep enough for ordinary recursion. The stack limit usually stops a run first, as every
+// Lox call takes a few kilobytes of Rust stack in release builds and tens of them in debug.
+const DEFAULT_MAX_CALL_DEPTH: usize = 512;
+
+// Leaves room for the host on a thread with the 2 MiB Rust spawns by default.
+const DEFAULT_MAX_STACK: usize = 1024 * 1024;
+
+// Bounds on the work a script can do. Only the call depth is limited by default, the
+// others are meant for running untrusted code.
+#[derive(Clone)]
+pub struct Limits {
+    pub(super) fuel: Option<u64>,
+    pub(super) max_call_depth: usize,
+    pub(super) max_stack: usize,
+    pub(super) timeout: Option<Duration>,
+    pub(super) max_heap: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            fuel: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_stack: DEFAULT_MAX_STACK,
+            timeout: None,
+            max_heap: None,
+        }
+    }
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Maximum number of statements and expressions evaluated in one run.
+    pub fn fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    pub fn max_call_depth(mut self, depth: usize) -> Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    // Bytes of Rust stack nested Lox calls may use, raise it when running on a bigger thread.
+    pub fn max_stack(mut self, bytes: usize) -> Self {
+        self.max_stack = bytes;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    // Approximate cap on the bytes of strings, lists, maps and instances a run allocates.
+    // Memory freed during the run isn't given back to the budget.
+    pub fn max_heap(mut self, bytes: usize) -> Self {
+        self.max_heap = Some(bytes);
+        self
+    }
+}
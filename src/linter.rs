@@ -0,0 +1,531 @@
+use std::{collections::HashSet, fmt, mem};
+
+use crate::{
+    ast_printer::AstPrinter,
+    compile,
+    custom_scanner::Scanner,
+    expression::Expr,
+    interpreter::Interpreter,
+    lox_error::SyntaxError,
+    statement::{FunctionDecl, Stmt},
+    token::{Span, Token},
+    token_type::TokenType,
+};
+
+// Every rule the linter knows, with what it warns about.
+pub const RULES: [(&str, &str); 8] = [
+    ("unused-variable", "a local variable is never read"),
+    ("unused-parameter", "a parameter is never read"),
+    (
+        "unreachable-code",
+        "a statement follows a return, throw or break",
+    ),
+    (
+        "shadowed-variable",
+        "a variable hides one declared in an enclosing scope",
+    ),
+    (
+        "assignment-in-condition",
+        "an if or while condition is an assignment",
+    ),
+    (
+        "self-comparison",
+        "a value is compared with itself, like `x == x`",
+    ),
+    (
+        "self-assignment",
+        "a variable or property is assigned to itself",
+    ),
+    ("empty-block", "a control flow statement has an empty block"),
+];
+
+// Comments starting with this silence the listed rules, on their own line when they follow
+// code and on the next line otherwise: `// lox-ignore: unused-variable, empty-block`.
+const IGNORE_COMMENT: &str = "// lox-ignore:";
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: warning[{}]: {}",
+            self.span.line, self.span.column, self.rule, self.message
+        )
+    }
+}
+
+// Finds code which is valid but likely a mistake. Every rule is on unless allowed:
+//
+//     let diagnostics = Linter::new().allow("shadowed-variable").lint(source)?;
+#[derive(Default)]
+pub struct Linter {
+    allowed: HashSet<String>,
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, rule: &str) -> Self {
+        self.allowed.insert(rule.to_string());
+        self
+    }
+
+    // Code which doesn't compile can't be linted, its errors are returned instead.
+    pub fn lint(&self, source: &str) -> Result<Vec<Diagnostic>, Vec<SyntaxError>> {
+        let statements = compile(source, &mut Interpreter::new())?;
+
+        let mut pass = LintPass {
+            lines: source.lines().collect(),
+            scopes: vec![Vec::new()],
+            diagnostics: Vec::new(),
+        };
+        pass.statements(&statements);
+        pass.end_scope();
+
+        let ignored = ignore_comments(source);
+        let mut diagnostics: Vec<Diagnostic> = pass
+            .diagnostics
+            .into_iter()
+            .filter(|diagnostic| !self.allowed.contains(diagnostic.rule))
+            .filter(|diagnostic| !ignored.contains(&(diagnostic.span.line, diagnostic.rule)))
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.span.line, diagnostic.span.column));
+        Ok(diagnostics)
+    }
+}
+
+// The lines and rules silenced by `// lox-ignore:` comments.
+fn ignore_comments(source: &str) -> HashSet<(u32, &'static str)> {
+    let mut scanner = Scanner::with_comments(source);
+    let tokens = scanner.scan_tokens().to_vec();
+
+    let mut ignored = HashSet::new();
+    let mut code_line = 0;
+    for token in tokens {
+        if token.c_type != TokenType::Comment {
+            code_line = token.line;
+            continue;
+        }
+        let Some(rules) = token.lexeme.strip_prefix(IGNORE_COMMENT) else {
+            continue;
+        };
+
+        let line = if code_line == token.line {
+            token.line
+        } else {
+            token.line + 1
+        };
+        for rule in rules.split(',').map(str::trim) {
+            if let Some((rule, _)) = RULES.iter().find(|(name, _)| *name == rule) {
+                ignored.insert((line, *rule));
+            }
+        }
+    }
+    ignored
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LocalKind {
+    Global,
+    Variable,
+    Parameter,
+    CatchVariable,
+}
+
+struct Local {
+    name: Token,
+    kind: LocalKind,
+    used: bool,
+}
+
+struct LintPass<'s> {
+    lines: Vec<&'s str>,
+    // The first scope holds the globals, which are never reported as unused.
+    scopes: Vec<Vec<Local>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl LintPass<'_> {
+    fn statements(&mut self, statements: &[Stmt]) {
+        // After a return, throw or break what follows is unreachable.
+        let mut after_exit = false;
+        for statement in statements {
+            if mem::take(&mut after_exit) {
+                // One warning is enough for the whole dead stretch.
+                self.report_line(
+                    "unreachable-code",
+                    "Unreachable code.".to_string(),
+                    statement.line(),
+                );
+            }
+            self.statement(statement);
+            after_exit = matches!(statement, Stmt::Return(_) | Stmt::Throw(_) | Stmt::Break(_));
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        self.begin_scope();
+        self.statements(statements);
+        self.end_scope();
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(block) => self.block(&block.statements),
+            Stmt::Break(_) => (),
+            Stmt::Class(class) => {
+                self.declare(&class.name, LocalKind::Variable);
+                if let Some(superclass) = &class.superclass {
+                    self.expression(superclass);
+                }
+                for method in &class.methods {
+                    self.function(method);
+                }
+            }
+            Stmt::Export(declaration) => self.statement(declaration),
+            Stmt::Expression(expr) => self.expression(expr),
+            Stmt::Print(print) => self.expression(&print.value),
+            Stmt::ForIn(for_in) => {
+                self.expression(&for_in.iterable);
+                self.begin_scope();
+                self.declare(&for_in.name, LocalKind::Variable);
+                self.empty_block(&for_in.body, "for", for_in.name.line);
+                self.statement(&for_in.body);
+                self.end_scope();
+            }
+            Stmt::Function(function) => {
+                if let Some(name) = &function.name {
+                    self.declare(name, LocalKind::Variable);
+                }
+                self.function(function);
+            }
+            Stmt::If(if_stmt) => {
+                self.condition(&if_stmt.condition);
+                self.empty_block(&if_stmt.then_branch, "if", if_stmt.keyword.line);
+                self.statement(&if_stmt.then_branch);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.empty_block(else_branch, "else", if_stmt.keyword.line);
+                    self.statement(else_branch);
+                }
+            }
+            Stmt::Import(import) => {
+                for name in import.alias.iter().chain(&import.names) {
+                    self.declare(name, LocalKind::Global);
+                }
+            }
+            Stmt::Return(return_stmt) => {
+                if let Some(value) = &return_stmt.value {
+                    self.expression(value);
+                }
+            }
+            Stmt::Throw(throw) => self.expression(&throw.value),
+            Stmt::Try(try_stmt) => {
+                if try_stmt.body.is_empty() {
+                    self.report_empty_block("try", try_stmt.keyword.line);
+                }
+                self.block(&try_stmt.body);
+
+                if let Some(catch) = &try_stmt.catch {
+                    if catch.body.is_empty() {
+                        self.report_empty_block("catch", catch.name.line);
+                    }
+                    self.begin_scope();
+                    self.declare(&catch.name, LocalKind::CatchVariable);
+                    self.statements(&catch.body);
+                    self.end_scope();
+                }
+
+                if let Some(finally) = &try_stmt.finally {
+                    if finally.is_empty() {
+                        self.report_empty_block("finally", try_stmt.keyword.line);
+                    }
+                    self.block(finally);
+                }
+            }
+            Stmt::Var(var_stmt) => {
+                if let Some(initializer) = &var_stmt.initializer {
+                    self.expression(initializer);
+                }
+                self.declare(&var_stmt.name, LocalKind::Variable);
+            }
+            Stmt::While(while_stmt) => {
+                self.condition(&while_stmt.condition);
+                // A `for` loop with an increment runs it in a block after its body.
+                let body = match &while_stmt.body {
+                    Stmt::Block(block) if block.brace.c_type == TokenType::For => {
+                        &block.statements[0]
+                    }
+                    body => body,
+                };
+                self.empty_block(body, &while_stmt.keyword.lexeme, while_stmt.keyword.line);
+                self.statement(&while_stmt.body);
+            }
+        }
+    }
+
+    fn function(&mut self, function: &FunctionDecl) {
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param, LocalKind::Parameter);
+        }
+        self.statements(&function.body);
+        self.end_scope();
+    }
+
+    fn condition(&mut self, condition: &Expr) {
+        let mut expr = condition;
+        while let Expr::Grouping(inner) = expr {
+            expr = inner;
+        }
+
+        let target = match expr {
+            Expr::Assign(assign) => Some(&assign.name),
+            Expr::Set(set) => Some(&set.name),
+            Expr::SetIndex(set_index) => Some(&set_index.bracket),
+            _ => None,
+        };
+        if let Some(target) = target {
+            self.report(
+                "assignment-in-condition",
+                "Assignment used as a condition, did you mean '=='?".to_string(),
+                target,
+            );
+        }
+
+        self.expression(condition);
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign(assign) => {
+                if let Expr::Variable(variable) = assign.value.as_ref() {
+                    if variable.name.lexeme == assign.name.lexeme {
+                        self.report(
+                            "self-assignment",
+                            format!("'{}' is assigned to itself.", assign.name.lexeme),
+                            &assign.name,
+                        );
+                    }
+                }
+                self.expression(&assign.value);
+            }
+            Expr::Binary(binary) => {
+                let compares = matches!(
+                    binary.operator.c_type,
+                    TokenType::EqualEqual
+                        | TokenType::BangEqual
+                        | TokenType::Less
+                        | TokenType::LessEqual
+                        | TokenType::Greater
+                        | TokenType::GreaterEqual
+                );
+                if compares && same_place(&binary.left, &binary.right) {
+                    self.report(
+                        "self-comparison",
+                        format!("Both sides of '{}' are the same.", binary.operator.lexeme),
+                        &binary.operator,
+                    );
+                }
+                self.expression(&binary.left);
+                self.expression(&binary.right);
+            }
+            Expr::Call(call) => {
+                self.expression(&call.callee);
+                for argument in &call.arguments {
+                    self.expression(argument);
+                }
+            }
+            Expr::Get(get) => self.expression(&get.object),
+            Expr::Grouping(expr) => self.expression(expr),
+            Expr::Index(index) => {
+                self.expression(&index.object);
+                self.expression(&index.index);
+            }
+            Expr::Lambda(function) => self.function(function),
+            Expr::List(list) => {
+                for element in &list.elements {
+                    self.expression(element);
+                }
+            }
+            Expr::Literal(_) | Expr::Super(_) | Expr::This(_) => (),
+            Expr::Logical(logical) => {
+                self.expression(&logical.left);
+                self.expression(&logical.right);
+            }
+            Expr::Map(map) => {
+                for (key, value) in &map.entries {
+                    self.expression(key);
+                    self.expression(value);
+                }
+            }
+            Expr::Set(set) => {
+                if let Expr::Get(get) = set.value.as_ref() {
+                    if get.name.lexeme == set.name.lexeme && same_place(&get.object, &set.object) {
+                        self.report(
+                            "self-assignment",
+                            format!("Property '{}' is assigned to itself.", set.name.lexeme),
+                            &set.name,
+                        );
+                    }
+                }
+                self.expression(&set.value);
+                self.expression(&set.object);
+            }
+            Expr::SetIndex(set_index) => {
+                if let Expr::Index(index) = set_index.value.as_ref() {
+                    if same_place(&index.object, &set_index.object)
+                        && same_place(&index.index, &set_index.index)
+                    {
+                        self.report(
+                            "self-assignment",
+                            "Element is assigned to itself.".to_string(),
+                            &set_index.bracket,
+                        );
+                    }
+                }
+                self.expression(&set_index.value);
+                self.expression(&set_index.object);
+                self.expression(&set_index.index);
+            }
+            Expr::Unary(unary) => self.expression(&unary.right),
+            Expr::Variable(variable) => self.use_variable(&variable.name),
+        }
+    }
+
+    fn empty_block(&mut self, body: &Stmt, statement: &str, line: u32) {
+        if matches!(body, Stmt::Block(block) if block.statements.is_empty()) {
+            self.report_empty_block(statement, line);
+        }
+    }
+
+    fn report_empty_block(&mut self, statement: &str, line: u32) {
+        self.report_line("empty-block", format!("Empty {statement} block."), line);
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+
+        for local in scope {
+            // A leading underscore marks a variable as unused on purpose.
+            if local.used || local.name.lexeme.starts_with('_') {
+                continue;
+            }
+            match local.kind {
+                LocalKind::Variable => self.report(
+                    "unused-variable",
+                    format!("Local variable '{}' is never used.", local.name.lexeme),
+                    &local.name,
+                ),
+                LocalKind::Parameter => self.report(
+                    "unused-parameter",
+                    format!("Parameter '{}' is never used.", local.name.lexeme),
+                    &local.name,
+                ),
+                LocalKind::Global | LocalKind::CatchVariable => (),
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, kind: LocalKind) {
+        let is_global = self.scopes.len() == 1;
+        let shadowed = self.scopes[..self.scopes.len() - 1]
+            .iter()
+            .flatten()
+            .rev()
+            .find(|local| local.name.lexeme == name.lexeme)
+            .map(|local| local.name.line);
+        if let Some(line) = shadowed {
+            self.report(
+                "shadowed-variable",
+                format!(
+                    "'{}' shadows the variable declared on line {line}.",
+                    name.lexeme
+                ),
+                name,
+            );
+        }
+
+        let kind = if is_global { LocalKind::Global } else { kind };
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("the global scope is never popped");
+        scope.push(Local {
+            name: name.clone(),
+            kind,
+            used: false,
+        });
+    }
+
+    fn use_variable(&mut self, name: &Token) {
+        let local = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|local| local.name.lexeme == name.lexeme);
+        if let Some(local) = local {
+            local.used = true;
+        }
+    }
+
+    fn report(&mut self, rule: &'static str, message: String, token: &Token) {
+        self.diagnostics.push(Diagnostic {
+            rule,
+            message,
+            span: token.span(),
+        });
+    }
+
+    // For statements, the span covers the code on their line.
+    fn report_line(&mut self, rule: &'static str, message: String, line: u32) {
+        let text = (line as usize)
+            .checked_sub(1)
+            .and_then(|index| self.lines.get(index))
+            .copied()
+            .unwrap_or_default();
+        let indent = text.chars().take_while(|ch| ch.is_whitespace()).count() as u32;
+        let span = Span {
+            line,
+            column: indent + 1,
+            end_column: text.trim_end().chars().count() as u32 + 1,
+        };
+        self.diagnostics.push(Diagnostic {
+            rule,
+            message,
+            span,
+        });
+    }
+}
+
+// Whether both expressions read the same variable, property or element, without side
+// effects which could make them differ.
+fn same_place(a: &Expr, b: &Expr) -> bool {
+    is_pure(a) && is_pure(b) && AstPrinter.print(a) == AstPrinter.print(b)
+}
+
+// Whether evaluating the expression twice gives the same value.
+fn is_pure(expr: &Expr) -> bool {
+    match expr {
+        Expr::Variable(_) | Expr::This(_) | Expr::Literal(_) => true,
+        Expr::Get(get) => is_pure(&get.object),
+        Expr::Grouping(expr) => is_pure(expr),
+        Expr::Index(index) => is_pure(&index.object) && is_pure(&index.index),
+        Expr::Unary(unary) => is_pure(&unary.right),
+        _ => false,
+    }
+}
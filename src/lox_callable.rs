@@ -0,0 +1,48 @@
+use crate::{interpreter::Interpreter, runtime_error::Unwind, token::Token, value::Value};
+
+pub trait LoxCallable {
+    fn arity(&self) -> usize;
+
+    // `paren` is the closing paren of the call expression, used for error reporting.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Unwind>;
+}
+
+pub type NativeFn = dyn Fn(&mut Interpreter, &Token, Vec<Value>) -> Result<Value, Unwind>;
+
+// A function implemented in Rust, exposed to Lox as a global or as a method of a built-in type.
+pub struct NativeFunction {
+    arity: usize,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        arity: usize,
+        function: impl Fn(&mut Interpreter, &Token, Vec<Value>) -> Result<Value, Unwind> + 'static,
+    ) -> Self {
+        Self {
+            arity,
+            function: Box::new(function),
+        }
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Unwind> {
+        (self.function)(interpreter, paren, arguments)
+    }
+}
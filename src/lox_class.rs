@@ -0,0 +1,105 @@
+use std::{cell::RefCell, collections::HashMap, mem, rc::Rc};
+
+use crate::{
+    interpreter::Interpreter,
+    lox_callable::LoxCallable,
+    lox_function::LoxFunction,
+    lox_instance::LoxInstance,
+    native_class::NativeClass,
+    runtime_error::{RuntimeError, Unwind},
+    token::Token,
+    value::Value,
+};
+
+pub struct LoxClass {
+    pub(super) name: Rc<str>,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+    // Set for classes implemented in Rust, see `ClassBuilder`.
+    pub(super) native: Option<NativeClass>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: Rc<str>,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+            native: None,
+        }
+    }
+
+    pub fn new_native(name: Rc<str>, native: NativeClass) -> Self {
+        Self {
+            name,
+            superclass: None,
+            methods: HashMap::new(),
+            native: Some(native),
+        }
+    }
+
+    pub fn is_subclass_of(&self, other: &LoxClass) -> bool {
+        std::ptr::eq(self, other)
+            || self
+                .superclass
+                .as_ref()
+                .is_some_and(|superclass| superclass.is_subclass_of(other))
+    }
+
+    // Looks up the method on this class first and then walks up the inheritance chain.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        self.methods.get(name).cloned().or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name))
+        })
+    }
+}
+
+// Calling a class constructs a new instance and runs its `init` method, if any.
+impl LoxCallable for Rc<LoxClass> {
+    fn arity(&self) -> usize {
+        if let Some(native) = &self.native {
+            return native.constructor.as_ref().map_or(0, |(arity, _)| *arity);
+        }
+
+        self.find_method("init")
+            .map_or(0, |initializer| initializer.arity())
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Unwind> {
+        interpreter.allocate(mem::size_of::<LoxInstance>())?;
+
+        if let Some(native) = &self.native {
+            let Some((_, constructor)) = &native.constructor else {
+                return Err(RuntimeError::new(
+                    paren,
+                    format!("Class '{}' can't be constructed from Lox.", self.name),
+                )
+                .into());
+            };
+            let data =
+                constructor(&arguments).map_err(|message| RuntimeError::new(paren, message))?;
+            let instance = LoxInstance::new_native(Rc::clone(self), data);
+            return Ok(Value::Instance(Rc::new(RefCell::new(instance))));
+        }
+
+        let instance = Rc::new(RefCell::new(LoxInstance::new(Rc::clone(self))));
+        if let Some(initializer) = self.find_method("init") {
+            initializer
+                .bind(Rc::clone(&instance))
+                .call(interpreter, paren, arguments)?;
+        }
+
+        Ok(Value::Instance(instance))
+    }
+}
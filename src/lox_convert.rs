@@ -0,0 +1,283 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    rc::Rc,
+};
+
+use crate::{
+    lox_list,
+    lox_map::{self, LoxMap, MapKey},
+    value::Value,
+};
+
+// Rust values which can be handed to Lox code.
+pub trait IntoLox {
+    fn into_lox(self) -> Value;
+}
+
+// Rust values which can be read back out of Lox values.
+pub trait FromLox: Sized {
+    fn from_lox(value: &Value) -> Result<Self, FromLoxError>;
+}
+
+// A Lox value which doesn't fit the Rust type it was converted to.
+#[derive(Clone, Debug)]
+pub struct FromLoxError {
+    expected: String,
+    got: String,
+}
+
+impl FromLoxError {
+    pub fn new(expected: impl Into<String>, got: &Value) -> Self {
+        Self {
+            expected: expected.into(),
+            got: got.type_name().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FromLoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Expected {}, got {}.", self.expected, self.got)
+    }
+}
+
+impl std::error::Error for FromLoxError {}
+
+// So natives returning `Result<Value, String>` can use `?` on conversions.
+impl From<FromLoxError> for String {
+    fn from(error: FromLoxError) -> Self {
+        error.to_string()
+    }
+}
+
+impl IntoLox for Value {
+    fn into_lox(self) -> Value {
+        self
+    }
+}
+
+impl FromLox for Value {
+    fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoLox for () {
+    fn into_lox(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl IntoLox for bool {
+    fn into_lox(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl FromLox for bool {
+    fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            other => Err(FromLoxError::new("a bool", other)),
+        }
+    }
+}
+
+impl IntoLox for f64 {
+    fn into_lox(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl FromLox for f64 {
+    fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+        match value {
+            Value::Number(n) => Ok(*n),
+            other => Err(FromLoxError::new("a number", other)),
+        }
+    }
+}
+
+impl IntoLox for f32 {
+    fn into_lox(self) -> Value {
+        Value::Number(self.into())
+    }
+}
+
+impl FromLox for f32 {
+    fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+        f64::from_lox(value).map(|n| n as f32)
+    }
+}
+
+// Lox only has doubles, integers must be whole numbers within the range of the Rust type.
+macro_rules! integer_conversions {
+    ($($int:ty),*) => {$(
+        impl IntoLox for $int {
+            fn into_lox(self) -> Value {
+                Value::Number(self as f64)
+            }
+        }
+
+        impl FromLox for $int {
+            fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+                let expected = concat!("an integer that fits in ", stringify!($int));
+                // MAX + 1 is a power of two, exact as a float even where MAX rounds up to it.
+                let end = <$int>::MAX as f64 + 1.0;
+                match value {
+                    Value::Number(n) if n.fract() == 0.0 && *n >= <$int>::MIN as f64 && *n < end => {
+                        Ok(*n as $int)
+                    }
+                    Value::Number(n) => Err(FromLoxError {
+                        expected: expected.to_string(),
+                        got: n.to_string(),
+                    }),
+                    other => Err(FromLoxError::new(expected, other)),
+                }
+            }
+        }
+    )*};
+}
+
+integer_conversions!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl IntoLox for &str {
+    fn into_lox(self) -> Value {
+        Value::Str(self.into())
+    }
+}
+
+impl IntoLox for String {
+    fn into_lox(self) -> Value {
+        Value::Str(self.into())
+    }
+}
+
+impl IntoLox for Rc<str> {
+    fn into_lox(self) -> Value {
+        Value::Str(self)
+    }
+}
+
+impl FromLox for String {
+    fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+        match value {
+            Value::Str(s) => Ok(s.to_string()),
+            other => Err(FromLoxError::new("a string", other)),
+        }
+    }
+}
+
+impl FromLox for Rc<str> {
+    fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+        match value {
+            Value::Str(s) => Ok(Rc::clone(s)),
+            other => Err(FromLoxError::new("a string", other)),
+        }
+    }
+}
+
+// None is nil, and nil converts back to None.
+impl<T: IntoLox> IntoLox for Option<T> {
+    fn into_lox(self) -> Value {
+        self.map_or(Value::Nil, IntoLox::into_lox)
+    }
+}
+
+impl<T: FromLox> FromLox for Option<T> {
+    fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+        match value {
+            Value::Nil => Ok(None),
+            value => T::from_lox(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoLox> IntoLox for Vec<T> {
+    fn into_lox(self) -> Value {
+        lox_list::new_list(self.into_iter().map(IntoLox::into_lox).collect())
+    }
+}
+
+impl<T: FromLox> FromLox for Vec<T> {
+    fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+        match value {
+            Value::List(list) => list.borrow().iter().map(T::from_lox).collect(),
+            other => Err(FromLoxError::new("a list", other)),
+        }
+    }
+}
+
+// Lox maps keep insertion order, so a HashMap's keys are sorted to keep the result stable.
+impl<T: IntoLox> IntoLox for HashMap<String, T> {
+    fn into_lox(self) -> Value {
+        self.into_iter().collect::<BTreeMap<_, _>>().into_lox()
+    }
+}
+
+impl<T: IntoLox> IntoLox for BTreeMap<String, T> {
+    fn into_lox(self) -> Value {
+        let mut map = LoxMap::new();
+        for (key, value) in self {
+            let key: Rc<str> = key.into();
+            map.insert(
+                MapKey::Str(Rc::clone(&key)),
+                Value::Str(key),
+                value.into_lox(),
+            );
+        }
+        lox_map::new_map(map)
+    }
+}
+
+impl<T: FromLox> FromLox for HashMap<String, T> {
+    fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+        let Value::Map(map) = value else {
+            return Err(FromLoxError::new("a map", value));
+        };
+
+        map.borrow()
+            .iter()
+            .map(|(key, value)| Ok((String::from_lox(key)?, T::from_lox(value)?)))
+            .collect()
+    }
+}
+
+// Tuples are lists of a fixed length.
+macro_rules! tuple_conversions {
+    ($len:literal => $($name:ident),*) => {
+        impl<$($name: IntoLox),*> IntoLox for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn into_lox(self) -> Value {
+                let ($($name,)*) = self;
+                lox_list::new_list(vec![$($name.into_lox()),*])
+            }
+        }
+
+        impl<$($name: FromLox),*> FromLox for ($($name,)*) {
+            fn from_lox(value: &Value) -> Result<Self, FromLoxError> {
+                let expected = concat!("a list of length ", $len);
+                let Value::List(list) = value else {
+                    return Err(FromLoxError::new(expected, value));
+                };
+
+                let list = list.borrow();
+                if list.len() != $len {
+                    return Err(FromLoxError {
+                        expected: expected.to_string(),
+                        got: format!("a list of length {}", list.len()),
+                    });
+                }
+
+                let mut elements = list.iter();
+                Ok(($($name::from_lox(elements.next().unwrap())?,)*))
+            }
+        }
+    };
+}
+
+tuple_conversions!(1 => A);
+tuple_conversions!(2 => A, B);
+tuple_conversions!(3 => A, B, C);
+tuple_conversions!(4 => A, B, C, D);
@@ -0,0 +1,87 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+use crate::runtime_error::RuntimeError;
+
+// An error found before the code runs, by the scanner, parser or resolver.
+#[derive(Clone, Debug)]
+pub struct SyntaxError {
+    pub line: u32,
+    pub location: String, // " at 'token'", " at end" or empty
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}] Error{}: {}",
+            self.line, self.location, self.message
+        )
+    }
+}
+
+// Everything which can make running Lox code fail, as seen by the embedder.
+#[derive(Debug)]
+pub enum LoxError {
+    // The command line was malformed, the message says how.
+    Usage(String),
+    // All the errors found in the source, nothing was run.
+    Compile(Vec<SyntaxError>),
+    Runtime(RuntimeError),
+    // The script called `os.exit` with this code.
+    Exit(i32),
+    // The script itself couldn't be read.
+    NoInput(PathBuf, io::Error),
+    Io(io::Error),
+}
+
+impl LoxError {
+    // The process exit code for the error, following jlox and sysexits.h.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoxError::Usage(_) => 64,
+            LoxError::Compile(_) => 65,
+            LoxError::NoInput(..) => 66,
+            LoxError::Runtime(_) => 70,
+            LoxError::Io(_) => 74,
+            LoxError::Exit(code) => *code,
+        }
+    }
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoxError::Compile(errors) => {
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{error}")?;
+                }
+                Ok(())
+            }
+            LoxError::Usage(message) => write!(f, "{message}"),
+            LoxError::Runtime(error) => write!(f, "{error}"),
+            LoxError::Exit(code) => write!(f, "Script exited with code {code}."),
+            LoxError::NoInput(path, error) => {
+                write!(f, "Could not read '{}': {error}.", path.display())
+            }
+            LoxError::Io(error) => write!(f, "I/O error: {error}."),
+        }
+    }
+}
+
+impl Error for LoxError {}
+
+impl From<io::Error> for LoxError {
+    fn from(error: io::Error) -> Self {
+        LoxError::Io(error)
+    }
+}
+
+impl From<RuntimeError> for LoxError {
+    fn from(error: RuntimeError) -> Self {
+        LoxError::Runtime(error)
+    }
+}
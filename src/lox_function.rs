@@ -0,0 +1,102 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    environment::Environment, interpreter::Interpreter, lox_callable::LoxCallable,
+    lox_instance::LoxInstance, runtime_error::Unwind, statement::FunctionDecl, token::Token,
+    value::Value,
+};
+
+pub struct LoxFunction {
+    declaration: Rc<FunctionDecl>,
+    closure: Rc<RefCell<Environment>>,
+    // Top level scope of the module the function was declared in, where its globals live.
+    globals: Rc<RefCell<Environment>>,
+    // The file it was declared in, for stack traces.
+    file: Option<Rc<str>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<FunctionDecl>,
+        closure: Rc<RefCell<Environment>>,
+        globals: Rc<RefCell<Environment>>,
+        file: Option<Rc<str>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            declaration,
+            closure,
+            globals,
+            file,
+            is_initializer,
+        }
+    }
+
+    // Creates a copy of the method whose closure has `this` bound to the instance.
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+        environment.define("this", Value::Instance(instance));
+        LoxFunction::new(
+            Rc::clone(&self.declaration),
+            Rc::new(RefCell::new(environment)),
+            Rc::clone(&self.globals),
+            self.file.clone(),
+            self.is_initializer,
+        )
+    }
+
+    pub fn name(&self) -> Rc<str> {
+        match &self.declaration.name {
+            Some(name) => Rc::clone(&name.lexeme),
+            None => Rc::from("<lambda>"),
+        }
+    }
+
+    fn this(&self) -> Value {
+        Environment::get_at(&self.closure, 0, "this")
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, Unwind> {
+        let mut environment = Environment::new_enclosed(Rc::clone(&self.closure));
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, argument);
+        }
+
+        interpreter.push_frame(self.name(), self.file.clone(), paren.line)?;
+        let previous_globals = interpreter.replace_globals(Rc::clone(&self.globals));
+        let result =
+            interpreter.execute_block(&self.declaration.body, Rc::new(RefCell::new(environment)));
+        interpreter.replace_globals(previous_globals);
+        let result = interpreter.pop_frame(result);
+
+        match result {
+            Ok(()) if self.is_initializer => Ok(self.this()),
+            Ok(()) => Ok(Value::Nil),
+            // An initializer always returns `this`, even on an early `return;`.
+            Err(Unwind::Return(_)) if self.is_initializer => Ok(self.this()),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.declaration.name {
+            Some(name) => write!(f, "<fn {}>", name.lexeme),
+            None => write!(f, "<lambda>"),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    lox_callable::NativeFunction, lox_class::LoxClass, native_class::NativeData,
+    runtime_error::RuntimeError, token::Token, value::Value,
+};
+
+pub struct LoxInstance {
+    pub(super) class: Rc<LoxClass>,
+    fields: HashMap<String, Value>,
+    // The Rust struct behind instances of native classes.
+    native: Option<NativeData>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+            native: None,
+        }
+    }
+
+    pub fn new_native(class: Rc<LoxClass>, data: NativeData) -> Self {
+        Self {
+            native: Some(data),
+            ..Self::new(class)
+        }
+    }
+
+    pub fn native_data(&self) -> Option<NativeData> {
+        self.native.clone()
+    }
+
+    // Fields shadow methods, methods are bound to the instance on access.
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = instance.borrow().get_native(name) {
+            return Ok(value);
+        }
+
+        if let Some(value) = instance.borrow().fields.get(name.lexeme.as_ref()) {
+            return Ok(value.clone());
+        }
+
+        let method = instance.borrow().class.find_method(&name.lexeme);
+        match method {
+            Some(method) => Ok(Value::Function(Rc::new(method.bind(Rc::clone(instance))))),
+            None => Err(RuntimeError::new(
+                name,
+                format!("Undefined property '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    // Properties of native classes come from the Rust struct instead of the fields.
+    fn get_native(&self, name: &Token) -> Option<Value> {
+        let (native, data) = (self.class.native.as_ref()?, self.native.as_ref()?);
+
+        if let Some(getter) = native.getters.get(name.lexeme.as_ref()) {
+            return Some(getter(&*data.borrow()));
+        }
+
+        // The data stays borrowed for the whole call, so a host which reenters Lox while
+        // holding it gets an error rather than a panic.
+        let (arity, method) = native.methods.get(name.lexeme.as_ref())?;
+        let (method, data) = (Rc::clone(method), Rc::clone(data));
+        let name = Rc::clone(&name.lexeme);
+        let bound = NativeFunction::new(*arity, move |_, paren, arguments| {
+            let mut data = data.try_borrow_mut().map_err(|_| {
+                RuntimeError::new(paren, format!("Can't call '{name}' while it is running."))
+            })?;
+            method(&mut *data, &arguments)
+                .map_err(|message| RuntimeError::new(paren, message).into())
+        });
+        Some(Value::Native(Rc::new(bound)))
+    }
+
+    // Assignment from Lox code. Native properties go through their setter, if they have one.
+    pub fn set_property(
+        instance: &Rc<RefCell<LoxInstance>>,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        let mut instance = instance.borrow_mut();
+        if let (Some(native), Some(data)) = (&instance.class.native, &instance.native) {
+            if let Some(setter) = native.setters.get(name.lexeme.as_ref()) {
+                return setter(&mut *data.borrow_mut(), &value)
+                    .map_err(|message| RuntimeError::new(name, message));
+            }
+            if native.getters.contains_key(name.lexeme.as_ref())
+                || native.methods.contains_key(name.lexeme.as_ref())
+            {
+                return Err(RuntimeError::new(
+                    name,
+                    format!("Can't assign to read-only property '{}'.", name.lexeme),
+                ));
+            }
+        }
+
+        instance.set(&name.lexeme, value);
+        Ok(())
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<Value> {
+        self.fields.get(name).cloned()
+    }
+
+    pub fn set(&mut self, name: &str, value: Value) {
+        self.fields.insert(name.to_string(), value);
+    }
+}
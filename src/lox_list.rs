@@ -0,0 +1,155 @@
+use std::{cell::RefCell, mem, rc::Rc};
+
+use crate::{
+    lox_callable::NativeFunction, runtime_error::RuntimeError, token::Token, value::Value,
+};
+
+// Lists are shared by reference, every alias sees the mutations of the others.
+pub type ListRef = Rc<RefCell<Vec<Value>>>;
+
+// Converts a Lox value to an index into a list of `len` elements.
+// `allow_end` accepts `len` itself, for operations like insert and slice.
+pub fn check_index(
+    token: &Token,
+    index: &Value,
+    len: usize,
+    allow_end: bool,
+) -> Result<usize, RuntimeError> {
+    let Value::Number(n) = index else {
+        return Err(RuntimeError::new(token, "List index must be a number."));
+    };
+
+    if n.fract() != 0.0 {
+        return Err(RuntimeError::new(
+            token,
+            format!("List index must be an integer, got {n}."),
+        ));
+    }
+    if *n < 0.0 {
+        return Err(RuntimeError::new(
+            token,
+            format!("List index can't be negative, got {n}."),
+        ));
+    }
+
+    let limit = if allow_end { len + 1 } else { len };
+    if *n >= limit as f64 {
+        return Err(RuntimeError::new(
+            token,
+            format!("List index {n} out of bounds for length {len}."),
+        ));
+    }
+
+    Ok(*n as usize)
+}
+
+pub fn get(list: &ListRef, bracket: &Token, index: &Value) -> Result<Value, RuntimeError> {
+    let list = list.borrow();
+    let index = check_index(bracket, index, list.len(), false)?;
+    Ok(list[index].clone())
+}
+
+pub fn set(
+    list: &ListRef,
+    bracket: &Token,
+    index: &Value,
+    value: Value,
+) -> Result<(), RuntimeError> {
+    let mut list = list.borrow_mut();
+    let index = check_index(bracket, index, list.len(), false)?;
+    list[index] = value;
+    Ok(())
+}
+
+// Built-in methods are natives bound to the list they were accessed on.
+pub fn get_method(list: &ListRef, name: &Token) -> Result<Value, RuntimeError> {
+    let list = Rc::clone(list);
+
+    let method = match name.lexeme.as_ref() {
+        "push" => NativeFunction::new(1, move |interpreter, _, mut arguments| {
+            interpreter.allocate(mem::size_of::<Value>())?;
+            list.borrow_mut().push(arguments.remove(0));
+            Ok(Value::Nil)
+        }),
+        "pop" => NativeFunction::new(0, move |_, paren, _| {
+            list.borrow_mut()
+                .pop()
+                .ok_or_else(|| RuntimeError::new(paren, "Can't pop from an empty list.").into())
+        }),
+        "len" => NativeFunction::new(0, move |_, _, _| {
+            Ok(Value::Number(list.borrow().len() as f64))
+        }),
+        "insert" => NativeFunction::new(2, move |interpreter, paren, mut arguments| {
+            interpreter.allocate(mem::size_of::<Value>())?;
+            let value = arguments.remove(1);
+            let mut list = list.borrow_mut();
+            let index = check_index(paren, &arguments[0], list.len(), true)?;
+            list.insert(index, value);
+            Ok(Value::Nil)
+        }),
+        "remove" => NativeFunction::new(1, move |_, paren, arguments| {
+            let mut list = list.borrow_mut();
+            let index = check_index(paren, &arguments[0], list.len(), false)?;
+            Ok(list.remove(index))
+        }),
+        "slice" => NativeFunction::new(2, move |interpreter, paren, arguments| {
+            let list = list.borrow();
+            let start = check_index(paren, &arguments[0], list.len(), true)?;
+            let end = check_index(paren, &arguments[1], list.len(), true)?;
+            if start > end {
+                return Err(RuntimeError::new(
+                    paren,
+                    format!("Slice start {start} is after end {end}."),
+                )
+                .into());
+            }
+            interpreter.allocate((end - start) * mem::size_of::<Value>())?;
+            Ok(new_list(list[start..end].to_vec()))
+        }),
+        "map" => NativeFunction::new(1, move |interpreter, paren, arguments| {
+            let mut mapped = Vec::new();
+            for element in snapshot(&list) {
+                mapped.push(interpreter.call(&arguments[0], paren, vec![element])?);
+            }
+            interpreter.allocate(mapped.len() * mem::size_of::<Value>())?;
+            Ok(new_list(mapped))
+        }),
+        "filter" => NativeFunction::new(1, move |interpreter, paren, arguments| {
+            let mut filtered = Vec::new();
+            for element in snapshot(&list) {
+                if interpreter
+                    .call(&arguments[0], paren, vec![element.clone()])?
+                    .is_truthy()
+                {
+                    filtered.push(element);
+                }
+            }
+            interpreter.allocate(filtered.len() * mem::size_of::<Value>())?;
+            Ok(new_list(filtered))
+        }),
+        "reduce" => NativeFunction::new(2, move |interpreter, paren, mut arguments| {
+            let mut accumulator = arguments.remove(1);
+            for element in snapshot(&list) {
+                accumulator = interpreter.call(&arguments[0], paren, vec![accumulator, element])?;
+            }
+            Ok(accumulator)
+        }),
+        _ => {
+            return Err(RuntimeError::new(
+                name,
+                format!("Undefined property '{}'.", name.lexeme),
+            ))
+        }
+    };
+
+    Ok(Value::Native(Rc::new(method)))
+}
+
+pub fn new_list(elements: Vec<Value>) -> Value {
+    Value::List(Rc::new(RefCell::new(elements)))
+}
+
+// Callbacks may mutate the list, so iterate over a copy instead of holding the borrow.
+fn snapshot(list: &ListRef) -> Vec<Value> {
+    list.borrow().clone()
+}
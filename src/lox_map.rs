@@ -0,0 +1,200 @@
+use std::{cell::RefCell, collections::HashMap, mem, rc::Rc};
+
+use crate::{
+    interpreter::Interpreter,
+    lox_callable::NativeFunction,
+    lox_instance::LoxInstance,
+    lox_list,
+    runtime_error::{RuntimeError, Unwind},
+    token::Token,
+    token_type::TokenType,
+    value::Value,
+};
+
+// Maps are shared by reference, like lists.
+pub type MapRef = Rc<RefCell<LoxMap>>;
+
+// Approximate size of one entry, charged against the interpreter's heap limit.
+pub const ENTRY_SIZE: usize = mem::size_of::<(MapKey, Value, Value)>();
+
+// The hashable form of a key. Instances are keyed by whatever their `hash` method returns.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Nil,
+    Bool(bool),
+    Number(u64), // bits of the f64, with 0 and -0 folded together
+    Str(Rc<str>),
+}
+
+impl MapKey {
+    pub fn new(interpreter: &mut Interpreter, token: &Token, key: &Value) -> Result<Self, Unwind> {
+        match key {
+            Value::Instance(instance) => {
+                let hash_name = Token::new(TokenType::Identifier, "hash", None, token.line);
+                let Ok(hash) = LoxInstance::get(instance, &hash_name) else {
+                    return Err(RuntimeError::new(
+                        token,
+                        "Instances used as map keys must define a 'hash' method.",
+                    )
+                    .into());
+                };
+
+                let hashed = interpreter.call(&hash, token, Vec::new())?;
+                Self::from_primitive(&hashed).ok_or_else(|| {
+                    RuntimeError::new(
+                        token,
+                        "A 'hash' method must return a string, number, bool or nil.",
+                    )
+                    .into()
+                })
+            }
+            key => Self::from_primitive(key).ok_or_else(|| {
+                RuntimeError::new(token, format!("Unhashable map key '{key}'.")).into()
+            }),
+        }
+    }
+
+    fn from_primitive(value: &Value) -> Option<Self> {
+        match value {
+            Value::Nil => Some(MapKey::Nil),
+            Value::Bool(b) => Some(MapKey::Bool(*b)),
+            Value::Number(n) if *n == 0.0 => Some(MapKey::Number(0.0f64.to_bits())),
+            Value::Number(n) => Some(MapKey::Number(n.to_bits())),
+            Value::Str(s) => Some(MapKey::Str(Rc::clone(s))),
+            _ => None,
+        }
+    }
+}
+
+// Keeps insertion order, so printing and iterating a map is deterministic.
+#[derive(Default)]
+pub struct LoxMap {
+    indices: HashMap<MapKey, usize>,
+    // The original key is kept alongside the value so `keys()` can hand back instances.
+    entries: Vec<(MapKey, Value, Value)>,
+}
+
+impl LoxMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<Value> {
+        self.indices
+            .get(key)
+            .map(|&index| self.entries[index].2.clone())
+    }
+
+    pub fn contains(&self, key: &MapKey) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: MapKey, original_key: Value, value: Value) {
+        match self.indices.get(&key) {
+            Some(&index) => self.entries[index].2 = value,
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, original_key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> Option<Value> {
+        let index = self.indices.remove(key)?;
+        let (_, _, value) = self.entries.remove(index);
+
+        // Entries after the removed one shifted down by one.
+        for slot in self.indices.values_mut() {
+            if *slot > index {
+                *slot -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn keys(&self) -> Vec<Value> {
+        self.entries.iter().map(|(_, key, _)| key.clone()).collect()
+    }
+
+    pub fn values(&self) -> Vec<Value> {
+        self.entries
+            .iter()
+            .map(|(_, _, value)| value.clone())
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> {
+        self.entries.iter().map(|(_, key, value)| (key, value))
+    }
+}
+
+// Missing keys read as nil, use `has` to tell them apart from stored nils.
+pub fn get(
+    interpreter: &mut Interpreter,
+    map: &MapRef,
+    bracket: &Token,
+    key: &Value,
+) -> Result<Value, Unwind> {
+    let key = MapKey::new(interpreter, bracket, key)?;
+    Ok(map.borrow().get(&key).unwrap_or(Value::Nil))
+}
+
+pub fn set(
+    interpreter: &mut Interpreter,
+    map: &MapRef,
+    bracket: &Token,
+    key: Value,
+    value: Value,
+) -> Result<(), Unwind> {
+    let hashed = MapKey::new(interpreter, bracket, &key)?;
+    if !map.borrow().contains(&hashed) {
+        interpreter.allocate(ENTRY_SIZE)?;
+    }
+    map.borrow_mut().insert(hashed, key, value);
+    Ok(())
+}
+
+// Built-in methods are natives bound to the map they were accessed on.
+pub fn get_method(map: &MapRef, name: &Token) -> Result<Value, RuntimeError> {
+    let map = Rc::clone(map);
+
+    let method = match name.lexeme.as_ref() {
+        "has" => NativeFunction::new(1, move |interpreter, paren, arguments| {
+            let key = MapKey::new(interpreter, paren, &arguments[0])?;
+            Ok(Value::Bool(map.borrow().contains(&key)))
+        }),
+        "remove" => NativeFunction::new(1, move |interpreter, paren, arguments| {
+            let key = MapKey::new(interpreter, paren, &arguments[0])?;
+            Ok(map.borrow_mut().remove(&key).unwrap_or(Value::Nil))
+        }),
+        "keys" => NativeFunction::new(0, move |_, _, _| {
+            Ok(lox_list::new_list(map.borrow().keys()))
+        }),
+        "values" => NativeFunction::new(0, move |_, _, _| {
+            Ok(lox_list::new_list(map.borrow().values()))
+        }),
+        "len" => NativeFunction::new(0, move |_, _, _| {
+            Ok(Value::Number(map.borrow().len() as f64))
+        }),
+        _ => {
+            return Err(RuntimeError::new(
+                name,
+                format!("Undefined property '{}'.", name.lexeme),
+            ))
+        }
+    };
+
+    Ok(Value::Native(Rc::new(method)))
+}
+
+pub fn new_map(map: LoxMap) -> Value {
+    Value::Map(Rc::new(RefCell::new(map)))
+}
@@ -0,0 +1,106 @@
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    env, fmt,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    environment::Environment, runtime_error::RuntimeError, statement::Stmt, token::Token,
+    value::Value,
+};
+
+// A loaded .lox file. Only its exported top level names can be read from the outside.
+pub struct LoxModule {
+    pub(super) path: PathBuf,
+    environment: Rc<RefCell<Environment>>,
+    exports: HashSet<String>,
+}
+
+impl LoxModule {
+    pub fn new(path: PathBuf, environment: Rc<RefCell<Environment>>, statements: &[Stmt]) -> Self {
+        Self {
+            path,
+            environment,
+            exports: exported_names(statements),
+        }
+    }
+
+    // A module implemented in Rust, every member is exported.
+    pub fn native(name: &str, members: Vec<(&str, Value)>) -> Self {
+        let mut environment = Environment::new();
+        let mut exports = HashSet::new();
+        for (member, value) in members {
+            environment.define(member, value);
+            exports.insert(member.to_string());
+        }
+
+        Self {
+            path: PathBuf::from(name),
+            environment: Rc::new(RefCell::new(environment)),
+            exports,
+        }
+    }
+
+    // Exports are live, reading one after the module changed it sees the new value.
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if !self.exports.contains(name.lexeme.as_ref()) {
+            return Err(RuntimeError::new(
+                name,
+                format!(
+                    "Module '{}' has no export named '{}'.",
+                    display_path(&self.path),
+                    name.lexeme
+                ),
+            ));
+        }
+
+        self.environment.borrow().get(name)
+    }
+}
+
+impl fmt::Display for LoxModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<module {}>", display_path(&self.path))
+    }
+}
+
+fn exported_names(statements: &[Stmt]) -> HashSet<String> {
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Stmt::Export(declaration) => match declaration.as_ref() {
+                Stmt::Class(class) => Some(class.name.lexeme.to_string()),
+                Stmt::Function(function) => {
+                    function.name.as_ref().map(|name| name.lexeme.to_string())
+                }
+                Stmt::Var(var_stmt) => Some(var_stmt.name.lexeme.to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+// Import paths are relative to the importing file, or to the working directory for code
+// which doesn't come from a file.
+pub fn resolve_path(importer: Option<&Path>, path: &str) -> PathBuf {
+    let base = importer
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .or_else(|| env::current_dir().ok())
+        .unwrap_or_default();
+    let path = base.join(path);
+    path.canonicalize().unwrap_or(path)
+}
+
+// Shortens the path relative to the working directory, for error messages.
+pub fn display_path(path: &Path) -> String {
+    env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
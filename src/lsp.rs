@@ -0,0 +1,483 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    analysis::{Analysis, Symbol, SymbolKind},
+    custom_scanner::KEYWORDS,
+    interpreter::Interpreter,
+    linter::Linter,
+    lox_error::{LoxError, SyntaxError},
+    token::Span,
+};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const PARSE_ERROR: i64 = -32700;
+const REQUEST_FAILED: i64 = -32803;
+
+// A language server speaking JSON-RPC over the given streams, normally stdin and stdout.
+// Documents are analyzed in full on every change. Returns once the client says `exit`,
+// which is only a success after a `shutdown`.
+pub fn serve(input: &mut impl BufRead, output: &mut impl Write) -> Result<(), LoxError> {
+    let mut server = Server::new();
+    while let Some(message) = read_message(input)? {
+        let replies = match serde_json::from_str::<Value>(&message) {
+            Ok(message) if message["method"] == "exit" => break,
+            Ok(message) => server.handle(&message),
+            Err(error) => vec![error_response(
+                &Value::Null,
+                PARSE_ERROR,
+                &error.to_string(),
+            )],
+        };
+        for reply in replies {
+            write_message(output, &reply)?;
+        }
+    }
+
+    if server.shut_down {
+        Ok(())
+    } else {
+        Err(LoxError::Exit(1))
+    }
+}
+
+fn read_message(input: &mut impl BufRead) -> Result<Option<String>, LoxError> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| invalid_data("Missing Content-Length header."))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|_| invalid_data("Message is not UTF-8."))?;
+    Ok(Some(body))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<(), LoxError> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()?;
+    Ok(())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct Document {
+    text: String,
+    analysis: Option<Analysis>,
+    // The analysis is of an earlier version, the text doesn't parse right now.
+    stale: bool,
+}
+
+struct Server {
+    documents: HashMap<String, Document>,
+    builtins: Vec<String>,
+    shut_down: bool,
+}
+
+impl Server {
+    fn new() -> Self {
+        Server {
+            documents: HashMap::new(),
+            builtins: Interpreter::new().global_names(),
+            shut_down: false,
+        }
+    }
+
+    // Returns the response to a request and any notifications to send.
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        let Some(id) = message.get("id") else {
+            return self.notification(method, params, uri);
+        };
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                    "renameProvider": true,
+                },
+                "serverInfo": { "name": "lox" },
+            })),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => Ok(self.definition(&uri, params)),
+            "textDocument/references" => Ok(self.references(&uri, params)),
+            "textDocument/hover" => Ok(self.hover(&uri, params)),
+            "textDocument/documentSymbol" => Ok(self.document_symbols(&uri)),
+            "textDocument/completion" => Ok(self.completion(&uri, params)),
+            "textDocument/rename" => self.rename(&uri, params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{method}'."))),
+        };
+
+        match result {
+            Ok(result) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            Err((code, message)) => vec![error_response(id, code, &message)],
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value, uri: String) -> Vec<Value> {
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            // Only full syncs are asked for, the last change is the whole text.
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![publish_diagnostics(&uri, Vec::new())];
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Vec::new();
+        };
+
+        let (analysis, stale) = match Analysis::new(text) {
+            Ok(analysis) => (Some(analysis), false),
+            Err(_) => {
+                let previous = self.documents.remove(&uri);
+                (previous.and_then(|document| document.analysis), true)
+            }
+        };
+        let diagnostics = diagnostics(text);
+        self.documents.insert(
+            uri.clone(),
+            Document {
+                text: text.to_string(),
+                analysis,
+                stale,
+            },
+        );
+        vec![publish_diagnostics(&uri, diagnostics)]
+    }
+
+    // The document and the symbol at the position of the request, if its analysis is current.
+    fn symbol_at(&self, uri: &str, params: &Value) -> Option<(&Document, &Symbol)> {
+        let document = self.documents.get(uri).filter(|document| !document.stale)?;
+        let (line, column) = from_position(&document.text, &params["position"])?;
+        let symbol = document.analysis.as_ref()?.symbol_at(line, column)?;
+        Some((document, symbol))
+    }
+
+    fn definition(&self, uri: &str, params: &Value) -> Value {
+        match self.symbol_at(uri, params) {
+            Some((document, symbol)) => location(uri, &document.text, &symbol.declaration),
+            None => Value::Null,
+        }
+    }
+
+    fn references(&self, uri: &str, params: &Value) -> Value {
+        let Some((document, symbol)) = self.symbol_at(uri, params) else {
+            return Value::Null;
+        };
+        let include_declaration = params["context"]["includeDeclaration"] == true;
+        let declaration = Some(&symbol.declaration).filter(|_| include_declaration);
+        let locations: Vec<Value> = declaration
+            .into_iter()
+            .chain(&symbol.references)
+            .map(|span| location(uri, &document.text, span))
+            .collect();
+        Value::from(locations)
+    }
+
+    fn hover(&self, uri: &str, params: &Value) -> Value {
+        let Some((document, symbol)) = self.symbol_at(uri, params) else {
+            return Value::Null;
+        };
+        let analysis = document.analysis.as_ref();
+        let parent = symbol
+            .parent
+            .and_then(|parent| analysis.map(|analysis| &analysis.symbols[parent]));
+        let kind = match (symbol.kind, parent) {
+            (SymbolKind::Method, Some(class)) => format!("method of class {}", class.name),
+            (SymbolKind::Parameter, Some(function)) => format!("parameter of {}", function.name),
+            (SymbolKind::Parameter, None) => String::from("parameter of a lambda"),
+            (SymbolKind::Import, _) => String::from("imported module name"),
+            (kind, _) => {
+                let scope = if symbol.global { "global" } else { "local" };
+                format!("{scope} {}", format!("{kind:?}").to_lowercase())
+            }
+        };
+
+        json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("```lox\n{}\n```\n{kind}", symbol.detail),
+            },
+        })
+    }
+
+    // Classes with their methods, and named functions.
+    fn document_symbols(&self, uri: &str) -> Value {
+        let Some(document) = self.documents.get(uri) else {
+            return Value::Null;
+        };
+        let Some(analysis) = document.analysis.as_ref().filter(|_| !document.stale) else {
+            return Value::from(Vec::<Value>::new());
+        };
+
+        let document_symbol = |symbol: &Symbol, kind: u8, children: Vec<Value>| {
+            let range = range(&document.text, &symbol.declaration);
+            json!({
+                "name": symbol.name,
+                "detail": symbol.detail,
+                "kind": kind,
+                "range": range,
+                "selectionRange": range,
+                "children": children,
+            })
+        };
+
+        let symbols: Vec<Value> = analysis
+            .symbols
+            .iter()
+            .enumerate()
+            .filter_map(|(index, symbol)| match symbol.kind {
+                SymbolKind::Class => {
+                    let methods = analysis
+                        .symbols
+                        .iter()
+                        .filter(|method| method.parent == Some(index))
+                        .filter(|method| method.kind == SymbolKind::Method)
+                        .map(|method| document_symbol(method, 6, Vec::new()))
+                        .collect();
+                    Some(document_symbol(symbol, 5, methods))
+                }
+                SymbolKind::Function => Some(document_symbol(symbol, 12, Vec::new())),
+                _ => None,
+            })
+            .collect();
+        Value::from(symbols)
+    }
+
+    // The names visible at the position, or after a `.` the methods and properties used
+    // anywhere in the document. Uses the last analysis while the text doesn't parse, which is
+    // most of the time while typing.
+    fn completion(&self, uri: &str, params: &Value) -> Value {
+        let Some(document) = self.documents.get(uri) else {
+            return Value::Null;
+        };
+        let position = from_position(&document.text, &params["position"]);
+        let after_dot = position.is_some_and(|(line, column)| {
+            let text = document
+                .text
+                .lines()
+                .nth(line as usize - 1)
+                .unwrap_or_default();
+            let before: String = text.chars().take(column as usize - 1).collect();
+            before
+                .trim_end_matches(|ch: char| ch.is_alphanumeric() || ch == '_')
+                .ends_with('.')
+        });
+
+        let mut items: Vec<(String, u8)> = Vec::new();
+        if let Some(analysis) = &document.analysis {
+            if after_dot {
+                let methods = analysis
+                    .symbols
+                    .iter()
+                    .filter(|symbol| symbol.kind == SymbolKind::Method)
+                    .map(|symbol| (symbol.name.clone(), 2));
+                let properties = analysis
+                    .properties
+                    .iter()
+                    .map(|property| (property.lexeme.to_string(), 10));
+                items.extend(methods.chain(properties));
+            } else {
+                let line = position.map_or(0, |(line, _)| line);
+                let visible = analysis.visible_at(line).into_iter().map(|symbol| {
+                    let kind = match symbol.kind {
+                        SymbolKind::Function => 3,
+                        SymbolKind::Class => 7,
+                        SymbolKind::Import => 9,
+                        _ => 6,
+                    };
+                    (symbol.name.clone(), kind)
+                });
+                items.extend(visible);
+            }
+        }
+        if !after_dot {
+            let keywords = KEYWORDS
+                .iter()
+                .map(|(keyword, _)| (keyword.to_string(), 14));
+            let builtins = self.builtins.iter().map(|name| (name.clone(), 3));
+            items.extend(keywords.chain(builtins));
+        }
+
+        // The innermost of several symbols with one name is the one in scope.
+        let mut seen = Vec::new();
+        items.retain(|(name, _)| {
+            let first = !seen.contains(name);
+            seen.push(name.clone());
+            first
+        });
+        let items: Vec<Value> = items
+            .into_iter()
+            .map(|(label, kind)| json!({ "label": label, "kind": kind }))
+            .collect();
+        Value::from(items)
+    }
+
+    fn rename(&self, uri: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let new_name = params["newName"].as_str().unwrap_or_default();
+        let mut chars = new_name.chars();
+        let is_identifier = chars
+            .next()
+            .is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+            && chars.all(|ch| ch.is_alphanumeric() || ch == '_');
+        let is_keyword = KEYWORDS.iter().any(|(keyword, _)| *keyword == new_name);
+        if !is_identifier || is_keyword {
+            return Err((INVALID_PARAMS, format!("'{new_name}' is not a valid name.")));
+        }
+
+        let Some((document, symbol)) = self.symbol_at(uri, params) else {
+            return Ok(Value::Null);
+        };
+        // Any property with the name may be the method, or a field of another class.
+        if symbol.kind == SymbolKind::Method {
+            return Err((
+                REQUEST_FAILED,
+                String::from("Methods and properties can't be renamed."),
+            ));
+        }
+        let edits: Vec<Value> = std::iter::once(&symbol.declaration)
+            .chain(&symbol.references)
+            .map(|span| json!({ "range": range(&document.text, span), "newText": new_name }))
+            .collect();
+        Ok(json!({ "changes": { uri: edits } }))
+    }
+}
+
+// Errors stop the analysis, so lint warnings are only shown for code which compiles.
+fn diagnostics(text: &str) -> Vec<Value> {
+    match Linter::new().lint(text) {
+        Ok(warnings) => warnings
+            .iter()
+            .map(|warning| {
+                json!({
+                    "range": range(text, &warning.span),
+                    "severity": 2,
+                    "code": warning.rule,
+                    "source": "lox",
+                    "message": warning.message,
+                })
+            })
+            .collect(),
+        Err(errors) => errors
+            .iter()
+            .map(|error| syntax_error_diagnostic(text, error))
+            .collect(),
+    }
+}
+
+// Errors only know their line, they cover all of it.
+fn syntax_error_diagnostic(text: &str, error: &SyntaxError) -> Value {
+    let length = text
+        .lines()
+        .nth(error.line.saturating_sub(1) as usize)
+        .map_or(0, |line| line.chars().count() as u32);
+    let span = Span {
+        line: error.line,
+        column: 1,
+        end_column: length + 1,
+    };
+    json!({
+        "range": range(text, &span),
+        "severity": 1,
+        "source": "lox",
+        "message": format!("Error{}: {}", error.location, error.message),
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn location(uri: &str, text: &str, span: &Span) -> Value {
+    json!({ "uri": uri, "range": range(text, span) })
+}
+
+fn range(text: &str, span: &Span) -> Value {
+    json!({
+        "start": position(text, span.line, span.column),
+        "end": position(text, span.line, span.end_column),
+    })
+}
+
+// Tokens count lines and columns in characters from 1, the protocol counts UTF-16 code units
+// from 0.
+fn position(text: &str, line: u32, column: u32) -> Value {
+    let line_text = text.lines().nth(line.saturating_sub(1) as usize);
+    let character: usize = line_text
+        .unwrap_or_default()
+        .chars()
+        .take(column.saturating_sub(1) as usize)
+        .map(char::len_utf16)
+        .sum();
+    json!({ "line": line.saturating_sub(1), "character": character })
+}
+
+fn from_position(text: &str, position: &Value) -> Option<(u32, u32)> {
+    let line = position["line"].as_u64()?;
+    let character = position["character"].as_u64()? as usize;
+    let line_text = text.lines().nth(line as usize).unwrap_or_default();
+
+    let mut units = 0;
+    let mut column = 1;
+    for ch in line_text.chars() {
+        if units >= character {
+            break;
+        }
+        units += ch.len_utf16();
+        column += 1;
+    }
+    Some((line as u32 + 1, column))
+}
@@ -1,10 +1,27 @@
-use std::{env, process};
+use std::{env, process, thread};
 
-use tree_walk_interpreter::lox;
+use tree_walk_interpreter::{lox, LoxError, STACK_SIZE};
 
 fn main() {
-    if let Err(e) = lox(env::args()) {
-        eprintln!("Application Error: {e}");
-        process::exit(1)
+    let code = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(|| exit_code(lox(env::args())))
+        .expect("failed to spawn the interpreter thread")
+        .join()
+        // The panic message was already printed by the thread.
+        .unwrap_or(101);
+
+    process::exit(code)
+}
+
+fn exit_code(result: Result<(), LoxError>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        // The script chose to exit, there is nothing to report.
+        Err(LoxError::Exit(code)) => code,
+        Err(error) => {
+            eprintln!("{error}");
+            error.exit_code()
+        }
     }
 }
@@ -0,0 +1,160 @@
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use crate::{
+    lox_class::LoxClass,
+    lox_convert::{FromLox, IntoLox},
+    lox_instance::LoxInstance,
+    value::Value,
+};
+
+// The Rust value behind an instance of a native class.
+pub type NativeData = Rc<RefCell<dyn Any>>;
+
+type Constructor = Box<dyn Fn(&[Value]) -> Result<NativeData, String>>;
+type Getter = Box<dyn Fn(&dyn Any) -> Value>;
+type Setter = Box<dyn Fn(&mut dyn Any, &Value) -> Result<(), String>>;
+type Method = Rc<dyn Fn(&mut dyn Any, &[Value]) -> Result<Value, String>>;
+
+// The Rust side of a class built with `ClassBuilder`, with the struct type erased.
+pub struct NativeClass {
+    type_id: TypeId,
+    pub(super) constructor: Option<(usize, Constructor)>,
+    pub(super) getters: HashMap<String, Getter>,
+    pub(super) setters: HashMap<String, Setter>,
+    pub(super) methods: HashMap<String, (usize, Method)>,
+}
+
+// Exposes a Rust struct to Lox as a class. Fields are read and written through the struct,
+// so Rust methods and Lox code always see the same data.
+//
+//     let point = ClassBuilder::<Point>::new("Point")
+//         .constructor(2, |args| Ok(Point { x: f64::from_lox(&args[0])?, y: f64::from_lox(&args[1])? }))
+//         .field("x", |p| p.x, |p, x| p.x = x)
+//         .method("length", 0, |p, _| Ok((p.x * p.x + p.y * p.y).sqrt()));
+//     lox.define_class(point);
+pub struct ClassBuilder<T> {
+    name: Rc<str>,
+    native: NativeClass,
+    struct_type: PhantomData<T>,
+}
+
+impl<T: 'static> ClassBuilder<T> {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            native: NativeClass {
+                type_id: TypeId::of::<T>(),
+                constructor: None,
+                getters: HashMap::new(),
+                setters: HashMap::new(),
+                methods: HashMap::new(),
+            },
+            struct_type: PhantomData,
+        }
+    }
+
+    // Without a constructor, instances can only be created from Rust with `instance`.
+    pub fn constructor(
+        mut self,
+        arity: usize,
+        constructor: impl Fn(&[Value]) -> Result<T, String> + 'static,
+    ) -> Self {
+        let constructor: Constructor = Box::new(move |arguments| {
+            let data: NativeData = Rc::new(RefCell::new(constructor(arguments)?));
+            Ok(data)
+        });
+        self.native.constructor = Some((arity, constructor));
+        self
+    }
+
+    pub fn field<V: IntoLox + FromLox>(
+        self,
+        name: &str,
+        get: impl Fn(&T) -> V + 'static,
+        set: impl Fn(&mut T, V) + 'static,
+    ) -> Self {
+        let mut builder = self.read_only_field(name, get);
+        let setter: Setter = Box::new(move |data, value| {
+            set(downcast_mut(data), V::from_lox(value)?);
+            Ok(())
+        });
+        builder.native.setters.insert(name.to_string(), setter);
+        builder
+    }
+
+    pub fn read_only_field<V: IntoLox>(
+        mut self,
+        name: &str,
+        get: impl Fn(&T) -> V + 'static,
+    ) -> Self {
+        let getter: Getter = Box::new(move |data| get(downcast(data)).into_lox());
+        self.native.getters.insert(name.to_string(), getter);
+        self
+    }
+
+    // The struct is borrowed mutably while the method runs, see `with_data`.
+    pub fn method<R: IntoLox>(
+        mut self,
+        name: &str,
+        arity: usize,
+        method: impl Fn(&mut T, &[Value]) -> Result<R, String> + 'static,
+    ) -> Self {
+        let method: Method = Rc::new(move |data, arguments| {
+            method(downcast_mut(data), arguments).map(IntoLox::into_lox)
+        });
+        self.native
+            .methods
+            .insert(name.to_string(), (arity, method));
+        self
+    }
+
+    pub fn build(self) -> Rc<LoxClass> {
+        Rc::new(LoxClass::new_native(self.name, self.native))
+    }
+}
+
+// Builders only hand their closures data created by the same builder, so the type matches.
+fn downcast<T: 'static>(data: &dyn Any) -> &T {
+    data.downcast_ref()
+        .expect("native data has the type of its class")
+}
+
+fn downcast_mut<T: 'static>(data: &mut dyn Any) -> &mut T {
+    data.downcast_mut()
+        .expect("native data has the type of its class")
+}
+
+// Wraps Rust data in a new instance of a native class, for handing it to Lox code.
+pub fn instance<T: 'static>(class: &Rc<LoxClass>, data: T) -> Value {
+    assert!(
+        class
+            .native
+            .as_ref()
+            .is_some_and(|native| native.type_id == TypeId::of::<T>()),
+        "class '{}' isn't a native class of this type",
+        class.name
+    );
+    let data: NativeData = Rc::new(RefCell::new(data));
+    Value::Instance(Rc::new(RefCell::new(LoxInstance::new_native(
+        Rc::clone(class),
+        data,
+    ))))
+}
+
+// Reads the Rust data behind an instance, None if the value isn't backed by a `T`. Also
+// None for the instance whose method is running, its data is borrowed mutably for the call,
+// so `p.add(p)` must be handled by the method.
+pub fn with_data<T: 'static, R>(value: &Value, f: impl FnOnce(&T) -> R) -> Option<R> {
+    let Value::Instance(instance) = value else {
+        return None;
+    };
+    let data = instance.borrow().native_data()?;
+    let data = data.try_borrow().ok()?;
+    data.downcast_ref().map(f)
+}
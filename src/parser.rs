@@ -1,208 +1,855 @@
+use std::rc::Rc;
+
 use crate::{
     expression::Expr,
+    statement::{CatchClause, FunctionDecl, Stmt},
     token::{Literal, Token},
+    token_error,
     token_type::TokenType,
 };
 
-pub struct Parser<'a> {
-    tokens: Vec<Token<'a>>,
+const MAX_ARGUMENTS: usize = 255;
+
+// Returned after the error has been reported, so the parser can synchronize.
+pub struct ParseError;
+
+type ParseResult<T> = Result<T, ParseError>;
+
+pub struct Parser {
+    tokens: Vec<Token>,
     current: usize,
 }
 
 // Eg code: 3 + 4 > 6 * (2 - 1) == true
-/**
- * expression     → equality ;
+/*
+ * program        → declaration* EOF ;
+ * declaration    → exportDecl | importDecl | classDecl | funDecl | varDecl | statement ;
+ * exportDecl     → "export" ( classDecl | funDecl | varDecl ) ;
+ * importDecl     → "import" STRING ( "as" IDENTIFIER )? ";"
+ *                | "import" "{" IDENTIFIER ( "," IDENTIFIER )* "}" "from" STRING ";" ;
+ * classDecl      → "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}" ;
+ * funDecl        → "fun" function ;
+ * function       → IDENTIFIER "(" parameters? ")" block ;
+ * parameters     → IDENTIFIER ( "," IDENTIFIER )* ;
+ * varDecl        → "var" IDENTIFIER ( "=" expression )? ";" ;
+ * statement      → exprStmt | breakStmt | forStmt | forInStmt | ifStmt | printStmt
+ *                | returnStmt | throwStmt | tryStmt | whileStmt | block ;
+ * breakStmt      → "break" ";" ;
+ * exprStmt       → expression ";" ;
+ * forStmt        → "for" "(" ( varDecl | exprStmt | ";" ) expression? ";" expression? ")" statement ;
+ * forInStmt      → "for" "(" "var" IDENTIFIER "in" expression ")" statement ;
+ * ifStmt         → "if" "(" expression ")" statement ( "else" statement )? ;
+ * printStmt      → "print" expression ";" ;
+ * returnStmt     → "return" expression? ";" ;
+ * throwStmt      → "throw" expression ";" ;
+ * tryStmt        → "try" block ( "catch" "(" IDENTIFIER ")" block )? ( "finally" block )? ;
+ * whileStmt      → "while" "(" expression ")" statement ;
+ * block          → "{" declaration* "}" ;
+ *
+ * expression     → assignment ;
+ * assignment     → ( call "." )? IDENTIFIER "=" assignment
+ *                | call "[" expression "]" "=" assignment | logic_or ;
+ * logic_or       → logic_and ( "or" logic_and )* ;
+ * logic_and      → equality ( "and" equality )* ;
  * equality       → comparison ( ( "!=" | "==" ) comparison )* ;
  * comparison     → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
  * term           → factor ( ( "-" | "+" ) factor )* ;
  * factor         → unary ( ( "/" | "*" ) unary )* ;
- * unary          → ( "!" | "-" ) unary | primary ;
- * primary        → NUMBER | STRING | "false" | "true" | "nil" | "(" expression ")" ;
+ * unary          → ( "!" | "-" ) unary | call ;
+ * call           → primary ( "(" arguments? ")" | "." IDENTIFIER | "[" expression "]" )* ;
+ * arguments      → expression ( "," expression )* ;
+ * primary        → NUMBER | STRING | "false" | "true" | "nil" | "this"
+ *                | IDENTIFIER | "(" expression ")" | "super" "." IDENTIFIER
+ *                | lambda | list | map ;
+ * list           → "[" ( expression ( "," expression )* ","? )? "]" ;
+ * map            → "{" ( entry ( "," entry )* ","? )? "}" ;
+ * entry          → expression ":" expression ;
+ *
+ * A "{" starting a statement is always a block, map literals only appear in expression position.
+ * lambda         → "fun" "(" parameters? ")" block
+ *                | "(" parameters? ")" "=>" ( block | expression ) ;
  */
 
 // This is a recursive descent parser
-impl<'a> Parser<'a> {
-    pub fn new(tokens: Vec<Token<'a>>) -> Self {
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
         Self { tokens, current: 0 }
     }
 
-    fn expression(&mut self) -> Expr<'a> {
-        self.equality()
+    // Parse errors are reported as they are found, the erroneous declarations are skipped.
+    pub fn parse(&mut self) -> Vec<Stmt> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+        statements
     }
 
-    fn equality(&mut self) -> Expr<'a> {
-        let mut expr = self.comparison();
+    fn declaration(&mut self) -> Option<Stmt> {
+        let result = if self.match_token(&[TokenType::Export]) {
+            self.export_declaration()
+        } else if self.match_token(&[TokenType::Import]) {
+            self.import_declaration()
+        } else {
+            self.plain_declaration()
+        };
 
-        while matches!(
-            self.peek(),
-            Some(token) if [
-                TokenType::EqualEqual,
-                TokenType::BangEqual,
-            ].contains(&token.c_type)
-        ) {
-            if let Some(operator) = self.advance() {
-                let operator = operator.clone();
-                let right = self.comparison();
-                expr = Expr::new_binary(expr, operator, right);
+        match result {
+            Ok(statement) => Some(statement),
+            Err(ParseError) => {
+                self.synchronize();
+                None
             }
         }
+    }
+
+    fn export_declaration(&mut self) -> ParseResult<Stmt> {
+        let is_declaration = self.check(&TokenType::Class)
+            || self.check(&TokenType::Var)
+            || (self.check(&TokenType::Fun) && self.check_next(&TokenType::Identifier));
+        if !is_declaration {
+            return Err(self.error(
+                self.peek_token(),
+                "Expect class, function or variable declaration after 'export'.",
+            ));
+        }
 
-        expr
+        Ok(Stmt::new_export(self.plain_declaration()?))
     }
 
-    fn comparison(&mut self) -> Expr<'a> {
-        let mut expr = self.term();
+    // `as` and `from` are only special here, elsewhere they are plain identifiers.
+    fn import_declaration(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.previous_token();
 
-        while matches!(
-            self.peek(),
-            Some(token) if [
-                TokenType::Greater,
-                TokenType::GreaterEqual,
-                TokenType::Less,
-                TokenType::LessEqual
-            ].contains(&token.c_type))
-        {
-            if let Some(operator) = self.advance() {
-                let operator = operator.clone();
-                let right = self.term();
-                expr = Expr::new_binary(expr, operator, right);
+        if self.match_token(&[TokenType::LeftBrace]) {
+            let mut names = Vec::new();
+            loop {
+                names.push(self.consume(&TokenType::Identifier, "Expect name to import.")?);
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
             }
+            self.consume(&TokenType::RightBrace, "Expect '}' after imported names.")?;
+            self.consume_contextual("from", "Expect 'from' after imported names.")?;
+            let path = self.consume(&TokenType::String, "Expect module path.")?;
+            self.consume(&TokenType::SemiColon, "Expect ';' after import.")?;
+            return Ok(Stmt::new_import(keyword, path, None, names));
+        }
+
+        let path = self.consume(&TokenType::String, "Expect module path after 'import'.")?;
+        let alias = if self.check_contextual("as") {
+            self.advance();
+            Some(self.consume(&TokenType::Identifier, "Expect module name after 'as'.")?)
+        } else {
+            None
+        };
+        self.consume(&TokenType::SemiColon, "Expect ';' after import.")?;
+        Ok(Stmt::new_import(keyword, path, alias, Vec::new()))
+    }
+
+    fn plain_declaration(&mut self) -> ParseResult<Stmt> {
+        if self.match_token(&[TokenType::Class]) {
+            self.class_declaration()
+        } else if self.check(&TokenType::Fun) && self.check_next(&TokenType::Identifier) {
+            // `fun` followed by a name is a declaration, otherwise it starts a lambda expression.
+            let keyword = self.advance_token();
+            self.function(keyword, "function").map(Stmt::new_function)
+        } else if self.match_token(&[TokenType::Var]) {
+            self.var_declaration()
+        } else {
+            self.statement()
+        }
+    }
+
+    fn class_declaration(&mut self) -> ParseResult<Stmt> {
+        let name = self.consume(&TokenType::Identifier, "Expect class name.")?;
+
+        let superclass = if self.match_token(&[TokenType::Less]) {
+            let superclass = self.consume(&TokenType::Identifier, "Expect superclass name.")?;
+            Some(Expr::new_variable(superclass))
+        } else {
+            None
+        };
+
+        self.consume(&TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            let name = self.peek_token().clone();
+            methods.push(Rc::new(self.function(name, "method")?));
         }
 
-        expr
+        self.consume(&TokenType::RightBrace, "Expect '}' after class body.")?;
+        Ok(Stmt::new_class(name, superclass, methods))
     }
 
-    fn term(&mut self) -> Expr<'a> {
-        let mut expr = self.factor();
+    fn function(&mut self, keyword: Token, kind: &str) -> ParseResult<FunctionDecl> {
+        let name = self.consume(&TokenType::Identifier, &format!("Expect {kind} name."))?;
+        self.consume(
+            &TokenType::LeftParen,
+            &format!("Expect '(' after {kind} name."),
+        )?;
+        let params = self.parameters()?;
+        self.consume(
+            &TokenType::LeftBrace,
+            &format!("Expect '{{' before {kind} body."),
+        )?;
+        let body = self.block()?;
+        Ok(FunctionDecl::new(keyword, Some(name), params, body))
+    }
 
-        while matches!(
-            self.peek(),
-            Some(token) if [
-                TokenType::Minus,
-                TokenType::Plus,
-            ].contains(&token.c_type)
-        ) {
-            if let Some(operator) = self.advance() {
-                let operator = operator.clone();
-                let right = self.factor();
-                expr = Expr::new_binary(expr, operator, right);
+    // Parses the parameter list after the opening paren, including the closing paren.
+    fn parameters(&mut self) -> ParseResult<Vec<Token>> {
+        let mut params = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    self.error(self.peek_token(), "Can't have more than 255 parameters.");
+                }
+                params.push(self.consume(&TokenType::Identifier, "Expect parameter name.")?);
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
             }
         }
+        self.consume(&TokenType::RightParen, "Expect ')' after parameters.")?;
+        Ok(params)
+    }
+
+    fn var_declaration(&mut self) -> ParseResult<Stmt> {
+        let name = self.consume(&TokenType::Identifier, "Expect variable name.")?;
+
+        let initializer = if self.match_token(&[TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(
+            &TokenType::SemiColon,
+            "Expect ';' after variable declaration.",
+        )?;
+        Ok(Stmt::new_var(name, initializer))
+    }
+
+    fn statement(&mut self) -> ParseResult<Stmt> {
+        if self.match_token(&[TokenType::Break]) {
+            let keyword = self.previous_token();
+            self.consume(&TokenType::SemiColon, "Expect ';' after 'break'.")?;
+            return Ok(Stmt::new_break(keyword));
+        }
+        if self.match_token(&[TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.match_token(&[TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.match_token(&[TokenType::Print]) {
+            return self.print_statement();
+        }
+        if self.match_token(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.match_token(&[TokenType::Throw]) {
+            let keyword = self.previous_token();
+            let value = self.expression()?;
+            self.consume(&TokenType::SemiColon, "Expect ';' after thrown value.")?;
+            return Ok(Stmt::new_throw(keyword, value));
+        }
+        if self.match_token(&[TokenType::Try]) {
+            return self.try_statement();
+        }
+        if self.match_token(&[TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.match_token(&[TokenType::LeftBrace]) {
+            let brace = self.previous_token();
+            return Ok(Stmt::new_block(brace, self.block()?));
+        }
+
+        self.expression_statement()
+    }
+
+    fn for_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.previous_token();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        if self.check(&TokenType::Var)
+            && self.check_next(&TokenType::Identifier)
+            && self
+                .tokens
+                .get(self.current + 2)
+                .is_some_and(|token| token.c_type == TokenType::In)
+        {
+            self.advance();
+            return self.for_in_statement();
+        }
+
+        let initializer = if self.match_token(&[TokenType::SemiColon]) {
+            None
+        } else if self.match_token(&[TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if !self.check(&TokenType::SemiColon) {
+            self.expression()?
+        } else {
+            Expr::new_literal(keyword.clone(), Literal::Bool(true))
+        };
+        self.consume(&TokenType::SemiColon, "Expect ';' after loop condition.")?;
+
+        let increment = if !self.check(&TokenType::RightParen) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume(&TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        // Desugar into a while loop, wrapped in blocks for the increment and the initializer.
+        let mut body = self.statement()?;
+        if let Some(increment) = increment {
+            body = Stmt::new_block(keyword.clone(), vec![body, Stmt::new_expression(increment)]);
+        }
+        body = Stmt::new_while(keyword.clone(), condition, body);
+        if let Some(initializer) = initializer {
+            body = Stmt::new_block(keyword, vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    // Parses the rest of `for (var x in iterable) body` after the `var`.
+    fn for_in_statement(&mut self) -> ParseResult<Stmt> {
+        let name = self.consume(&TokenType::Identifier, "Expect variable name.")?;
+        let keyword = self.consume(&TokenType::In, "Expect 'in' after loop variable.")?;
+        let iterable = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after for-in clause.")?;
+        let body = self.statement()?;
+
+        Ok(Stmt::new_for_in(name, keyword, iterable, body))
+    }
+
+    fn if_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.previous_token();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = self.statement()?;
+        let else_branch = if self.match_token(&[TokenType::Else]) {
+            Some(self.statement()?)
+        } else {
+            None
+        };
+
+        Ok(Stmt::new_if(keyword, condition, then_branch, else_branch))
+    }
+
+    fn print_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.previous_token();
+        let value = self.expression()?;
+        self.consume(&TokenType::SemiColon, "Expect ';' after value.")?;
+        Ok(Stmt::new_print(keyword, value))
+    }
+
+    fn return_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.previous_token();
+        let value = if !self.check(&TokenType::SemiColon) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(&TokenType::SemiColon, "Expect ';' after return value.")?;
+        Ok(Stmt::new_return(keyword, value))
+    }
+
+    fn try_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.previous_token();
+        self.consume(&TokenType::LeftBrace, "Expect '{' after 'try'.")?;
+        let body = self.block()?;
+
+        let catch = if self.match_token(&[TokenType::Catch]) {
+            self.consume(&TokenType::LeftParen, "Expect '(' after 'catch'.")?;
+            let name = self.consume(&TokenType::Identifier, "Expect error variable name.")?;
+            self.consume(&TokenType::RightParen, "Expect ')' after error variable.")?;
+            self.consume(&TokenType::LeftBrace, "Expect '{' before catch body.")?;
+            Some(CatchClause {
+                name,
+                body: self.block()?,
+            })
+        } else {
+            None
+        };
+
+        let finally = if self.match_token(&[TokenType::Finally]) {
+            self.consume(&TokenType::LeftBrace, "Expect '{' after 'finally'.")?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error(&keyword, "Expect 'catch' or 'finally' after try block."));
+        }
+
+        Ok(Stmt::new_try(keyword, body, catch, finally))
+    }
+
+    fn while_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.previous_token();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = self.statement()?;
+
+        Ok(Stmt::new_while(keyword, condition, body))
+    }
+
+    // Parses the declarations after the opening brace, including the closing brace.
+    fn block(&mut self) -> ParseResult<Vec<Stmt>> {
+        let mut statements = Vec::new();
+
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        self.consume(&TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    fn expression_statement(&mut self) -> ParseResult<Stmt> {
+        let expr = self.expression()?;
+        self.consume(&TokenType::SemiColon, "Expect ';' after expression.")?;
+        Ok(Stmt::new_expression(expr))
+    }
+
+    fn expression(&mut self) -> ParseResult<Expr> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> ParseResult<Expr> {
+        let expr = self.or()?;
+
+        if self.match_token(&[TokenType::Equal]) {
+            let equals = self.previous_token();
+            let value = self.assignment()?;
+
+            return match expr {
+                Expr::Variable(variable) => Ok(Expr::new_assign(variable.name, value)),
+                Expr::Get(get) => Ok(Expr::new_set(*get.object, get.name, value)),
+                Expr::Index(index) => Ok(Expr::new_set_index(
+                    *index.object,
+                    index.bracket,
+                    *index.index,
+                    index.span,
+                    value,
+                )),
+                expr => {
+                    // Report but don't synchronize, the parser is not in a confused state.
+                    self.error(&equals, "Invalid assignment target.");
+                    Ok(expr)
+                }
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.and()?;
+
+        while self.match_token(&[TokenType::Or]) {
+            let operator = self.previous_token();
+            let right = self.and()?;
+            expr = Expr::new_logical(expr, operator, right);
+        }
 
-        expr
+        Ok(expr)
     }
 
-    fn factor(&mut self) -> Expr<'a> {
-        let mut expr = self.unary();
+    fn and(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.equality()?;
 
-        while matches!(
-            self.peek(),
-            Some(token) if [
-                TokenType::Slash,
-                TokenType::Star,
-            ].contains(&token.c_type)
-        ) {
-            if let Some(operator) = self.advance() {
-                let operator = operator.clone();
-                let right = self.unary();
-                expr = Expr::new_binary(expr, operator, right);
+        while self.match_token(&[TokenType::And]) {
+            let operator = self.previous_token();
+            let right = self.equality()?;
+            expr = Expr::new_logical(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.comparison()?;
+
+        while self.match_token(&[TokenType::EqualEqual, TokenType::BangEqual]) {
+            let operator = self.previous_token();
+            let right = self.comparison()?;
+            expr = Expr::new_binary(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.term()?;
+
+        while self.match_token(&[
+            TokenType::Greater,
+            TokenType::GreaterEqual,
+            TokenType::Less,
+            TokenType::LessEqual,
+        ]) {
+            let operator = self.previous_token();
+            let right = self.term()?;
+            expr = Expr::new_binary(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.factor()?;
+
+        while self.match_token(&[TokenType::Minus, TokenType::Plus]) {
+            let operator = self.previous_token();
+            let right = self.factor()?;
+            expr = Expr::new_binary(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.unary()?;
+
+        while self.match_token(&[TokenType::Slash, TokenType::Star]) {
+            let operator = self.previous_token();
+            let right = self.unary()?;
+            expr = Expr::new_binary(expr, operator, right);
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> ParseResult<Expr> {
+        if self.match_token(&[TokenType::Bang, TokenType::Minus]) {
+            let operator = self.previous_token();
+            let right = self.unary()?;
+            return Ok(Expr::new_unary(operator, right));
+        }
+
+        self.call()
+    }
+
+    fn call(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_token(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_token(&[TokenType::Dot]) {
+                let name =
+                    self.consume(&TokenType::Identifier, "Expect property name after '.'.")?;
+                expr = Expr::new_get(expr, name);
+            } else if self.match_token(&[TokenType::LeftBracket]) {
+                let bracket = self.previous_token();
+                let index = self.expression()?;
+                let closing = self.consume(&TokenType::RightBracket, "Expect ']' after index.")?;
+                let span = bracket.span_to(&closing);
+                expr = Expr::new_index(expr, bracket, index, span);
+            } else {
+                break;
             }
         }
 
-        expr
+        Ok(expr)
     }
 
-    fn unary(&mut self) -> Expr<'a> {
-        if matches!(
-            self.peek(),
-            Some(token) if [
-                TokenType::Bang,
-                TokenType::Minus,
-            ].contains(&token.c_type)
-        ) {
-            if let Some(operator) = self.advance() {
-                let operator = operator.clone();
-                let right = self.unary();
-                return Expr::new_unary(operator, right);
+    fn finish_call(&mut self, callee: Expr) -> ParseResult<Expr> {
+        let mut arguments = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    self.error(self.peek_token(), "Can't have more than 255 arguments.");
+                }
+                arguments.push(self.expression()?);
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
             }
         }
 
-        self.primary()
+        let paren = self.consume(&TokenType::RightParen, "Expect ')' after arguments.")?;
+        Ok(Expr::new_call(callee, paren, arguments))
     }
 
-    fn primary(&mut self) -> Expr<'a> {
-        match self.peek() {
-            Some(token) if token.c_type == TokenType::False => {
-                self.advance();
-                return Expr::new_literal(Literal::Bool(false));
+    fn primary(&mut self) -> ParseResult<Expr> {
+        if self.match_token(&[TokenType::False]) {
+            return Ok(Expr::new_literal(
+                self.previous_token(),
+                Literal::Bool(false),
+            ));
+        }
+        if self.match_token(&[TokenType::True]) {
+            return Ok(Expr::new_literal(
+                self.previous_token(),
+                Literal::Bool(true),
+            ));
+        }
+        if self.match_token(&[TokenType::Nil]) {
+            return Ok(Expr::new_literal(self.previous_token(), Literal::Nil));
+        }
+        if self.match_token(&[TokenType::Number, TokenType::String]) {
+            let token = self.previous_token();
+            let literal = token
+                .literal
+                .clone()
+                .expect("Scanner always attaches a literal to numbers and strings");
+            return Ok(Expr::new_literal(token, literal));
+        }
+        if self.match_token(&[TokenType::Super]) {
+            let keyword = self.previous_token();
+            self.consume(&TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self.consume(&TokenType::Identifier, "Expect superclass method name.")?;
+            return Ok(Expr::new_super(keyword, method));
+        }
+        if self.match_token(&[TokenType::This]) {
+            return Ok(Expr::new_this(self.previous_token()));
+        }
+        if self.match_token(&[TokenType::Identifier]) {
+            return Ok(Expr::new_variable(self.previous_token()));
+        }
+        if self.match_token(&[TokenType::LeftBracket]) {
+            return self.list();
+        }
+        if self.match_token(&[TokenType::LeftBrace]) {
+            return self.map();
+        }
+        if self.match_token(&[TokenType::Fun]) {
+            let keyword = self.previous_token();
+            self.consume(&TokenType::LeftParen, "Expect '(' after 'fun'.")?;
+            let params = self.parameters()?;
+            self.consume(&TokenType::LeftBrace, "Expect '{' before lambda body.")?;
+            let body = self.block()?;
+            return Ok(Expr::new_lambda(FunctionDecl::new(
+                keyword, None, params, body,
+            )));
+        }
+        if self.check(&TokenType::LeftParen) && self.is_arrow_function() {
+            let paren = self.advance_token();
+            let params = self.parameters()?;
+            self.consume(&TokenType::Arrow, "Expect '=>' after lambda parameters.")?;
+            return self.arrow_body(paren, params);
+        }
+        if self.match_token(&[TokenType::LeftParen]) {
+            let expr = self.expression()?;
+            self.consume(&TokenType::RightParen, "Expect ')' after expression.")?;
+            return Ok(Expr::new_grouping(expr));
+        }
+
+        Err(self.error(self.peek_token(), "Expect expression."))
+    }
+
+    // Parses the elements after the opening bracket, a trailing comma is allowed.
+    fn list(&mut self) -> ParseResult<Expr> {
+        let bracket = self.previous_token();
+        let mut elements = Vec::new();
+
+        while !self.check(&TokenType::RightBracket) && !self.is_at_end() {
+            elements.push(self.expression()?);
+            if !self.match_token(&[TokenType::Comma]) {
+                break;
             }
-            Some(token) if token.c_type == TokenType::True => {
-                self.advance();
-                return Expr::new_literal(Literal::Bool(true));
+        }
+
+        self.consume(&TokenType::RightBracket, "Expect ']' after list elements.")?;
+        Ok(Expr::new_list(bracket, elements))
+    }
+
+    // Parses the entries after the opening brace, a trailing comma is allowed.
+    fn map(&mut self) -> ParseResult<Expr> {
+        let brace = self.previous_token();
+        let mut entries = Vec::new();
+
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            let key = self.expression()?;
+            self.consume(&TokenType::Colon, "Expect ':' after map key.")?;
+            let value = self.expression()?;
+            entries.push((key, value));
+            if !self.match_token(&[TokenType::Comma]) {
+                break;
             }
-            Some(token) if token.c_type == TokenType::Nil => {
-                self.advance();
-                return Expr::new_literal(Literal::Nil);
+        }
+
+        self.consume(&TokenType::RightBrace, "Expect '}' after map entries.")?;
+        Ok(Expr::new_map(brace, entries))
+    }
+
+    // An arrow body is either a block or a single expression which is implicitly returned.
+    fn arrow_body(&mut self, paren: Token, params: Vec<Token>) -> ParseResult<Expr> {
+        let body = if self.match_token(&[TokenType::LeftBrace]) {
+            self.block()?
+        } else {
+            let value = self.expression()?;
+            let keyword = Token::new(TokenType::Return, "return", None, paren.line);
+            vec![Stmt::new_return(keyword, Some(value))]
+        };
+
+        Ok(Expr::new_lambda(FunctionDecl::new(
+            paren, None, params, body,
+        )))
+    }
+
+    // Looks ahead, without consuming, for `( IDENTIFIER? ( "," IDENTIFIER )* ) =>`
+    // to tell an arrow function apart from a grouping expression.
+    fn is_arrow_function(&self) -> bool {
+        let mut idx = self.current + 1;
+        let c_type_at = |idx: usize| self.tokens.get(idx).map(|token| token.c_type);
+
+        if c_type_at(idx) != Some(TokenType::RightParen) {
+            loop {
+                if c_type_at(idx) != Some(TokenType::Identifier) {
+                    return false;
+                }
+                idx += 1;
+                if c_type_at(idx) != Some(TokenType::Comma) {
+                    break;
+                }
+                idx += 1;
             }
-            Some(token)
-                if token.c_type == TokenType::Number || token.c_type == TokenType::String =>
+        }
+
+        c_type_at(idx) == Some(TokenType::RightParen)
+            && c_type_at(idx + 1) == Some(TokenType::Arrow)
+    }
+
+    fn consume(&mut self, c_type: &TokenType, error_msg: &str) -> ParseResult<Token> {
+        if self.check(c_type) {
+            return Ok(self.advance_token());
+        }
+
+        Err(self.error(self.peek_token(), error_msg))
+    }
+
+    fn consume_contextual(&mut self, keyword: &str, error_msg: &str) -> ParseResult<Token> {
+        if self.check_contextual(keyword) {
+            return Ok(self.advance_token());
+        }
+
+        Err(self.error(self.peek_token(), error_msg))
+    }
+
+    fn check_contextual(&self, keyword: &str) -> bool {
+        self.check(&TokenType::Identifier)
+            && self
+                .peek()
+                .is_some_and(|token| token.lexeme.as_ref() == keyword)
+    }
+
+    fn error(&self, token: &Token, message: &str) -> ParseError {
+        token_error(token, message);
+        ParseError
+    }
+
+    // Discard tokens until we are probably at the start of the next statement.
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self
+                .previous()
+                .is_some_and(|token| token.c_type == TokenType::SemiColon)
             {
-                if let Some(literal) = token.literal {
+                return;
+            }
+
+            match self.peek().map(|token| token.c_type) {
+                Some(
+                    TokenType::Class
+                    | TokenType::Export
+                    | TokenType::Import
+                    | TokenType::Fun
+                    | TokenType::Var
+                    | TokenType::For
+                    | TokenType::If
+                    | TokenType::While
+                    | TokenType::Print
+                    | TokenType::Return
+                    | TokenType::Throw
+                    | TokenType::Try,
+                ) => return,
+                _ => {
                     self.advance();
-                    return Expr::new_literal(literal);
-                } else {
-                    panic!("Literal not found");
                 }
             }
-            Some(token) if token.c_type == TokenType::LeftParen => {
+        }
+    }
+
+    fn match_token(&mut self, types: &[TokenType]) -> bool {
+        for token_type in types {
+            if self.check(token_type) {
                 self.advance();
-                let expr = self.expression();
-                self.consume(&TokenType::RightParen, "Expect ')' after expression");
-                return Expr::new_grouping(expr);
+                return true;
             }
-            _ => panic!("Unexpected token"),
         }
+
+        false
+    }
+
+    fn check(&self, token_type: &TokenType) -> bool {
+        !self.is_at_end() && self.peek().is_some_and(|token| token.c_type == *token_type)
     }
 
-    fn consume(&mut self, token: &TokenType, error_msg: &str) -> Expr<'a> {
-        todo!("Implement consume")
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| token.c_type == *token_type)
     }
 
     pub fn is_at_end(&self) -> bool {
         self.peek()
-            .map_or(true, |token| token.c_type == TokenType::Eof)
+            .is_none_or(|token| token.c_type == TokenType::Eof)
     }
 
-    pub fn peek(&self) -> Option<&Token<'a>> {
+    pub fn peek(&self) -> Option<&Token> {
         self.tokens.get(self.current)
     }
 
     // consume the current token and return it
-    pub fn advance(&mut self) -> Option<&Token<'a>> {
+    pub fn advance(&mut self) -> Option<&Token> {
         if !self.is_at_end() {
             self.current += 1;
         }
         self.previous()
     }
 
-    pub fn previous(&self) -> Option<&Token<'a>> {
-        self.tokens.get(self.current - 1)
+    pub fn previous(&self) -> Option<&Token> {
+        self.tokens.get(self.current.checked_sub(1)?)
     }
 
-    // TODO: Remove the following as not needed
-    // fn match_token(&mut self, types: &[TokenType]) -> bool {
-    //     for token_type in types {
-    //         if self.check(token_type) {
-    //             self.advance();
-    //             return true;
-    //         }
-    //     }
+    // The scanner always ends the tokens with Eof, so the following never run past the end.
+    fn peek_token(&self) -> &Token {
+        self.peek()
+            .or(self.tokens.last())
+            .expect("Token stream always ends with Eof")
+    }
 
-    //     false
-    // }
+    fn advance_token(&mut self) -> Token {
+        self.advance();
+        self.previous_token()
+    }
 
-    // fn check(&self, token_type: &TokenType) -> bool {
-    //     self.peek()
-    //         .map_or(false, |token| token.c_type == *token_type)
-    // }
+    fn previous_token(&self) -> Token {
+        self.previous()
+            .cloned()
+            .expect("Only called after a token has been consumed")
+    }
 }
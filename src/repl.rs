@@ -0,0 +1,318 @@
+use std::{
+    borrow::Cow,
+    env,
+    io::{self, Write},
+    path::PathBuf,
+    time::Instant,
+};
+
+use rustyline::{
+    completion::Completer,
+    error::ReadlineError,
+    highlight::{CmdKind, Highlighter},
+    hint::Hinter,
+    history::FileHistory,
+    validate::Validator,
+    Context, Editor, Helper,
+};
+
+use crate::{
+    cli_builder,
+    custom_scanner::{Scanner, KEYWORDS},
+    embed::Lox,
+    highlighter,
+    lox_error::LoxError,
+    parse, print_ast, print_tokens,
+    statement::Stmt,
+    take_syntax_errors,
+    token_type::TokenType,
+    value::Value,
+};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+const HISTORY_FILE: &str = ".lox_history";
+
+const COMMANDS: [(&str, &str); 8] = [
+    (":help", "Show this help"),
+    (":env", "List the globals and their values"),
+    (
+        ":type <expr>",
+        "Show the type of the value of an expression",
+    ),
+    (":ast <code>", "Show the syntax tree of the code"),
+    (":tokens <code>", "Show the tokens of the code"),
+    (":load <file>", "Run a script, keeping what it defines"),
+    (":reset", "Forget every global"),
+    (":time <code>", "Run the code and show how long it took"),
+];
+
+// The interactive session started by `lox` without a script. Input is read until it forms
+// complete code, so functions and classes can span several lines. Globals live for the whole
+// session, and the value of an expression is printed without needing `print`.
+pub fn run_prompt() -> Result<(), LoxError> {
+    let mut lox = cli_builder().build();
+    let mut editor: Editor<LoxHelper, FileHistory> = Editor::new().map_err(readline_error)?;
+    editor.set_helper(Some(LoxHelper {
+        names: lox.global_names(),
+        highlighter: highlighter::Highlighter::new(),
+    }));
+
+    // There is no history yet on the first run.
+    let history = history_path();
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+
+    let result = read_eval_loop(&mut lox, &mut editor);
+
+    // Losing the history isn't worth failing the session for.
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
+    }
+    result
+}
+
+fn read_eval_loop(
+    lox: &mut Lox,
+    editor: &mut Editor<LoxHelper, FileHistory>,
+) -> Result<(), LoxError> {
+    let mut source = String::new();
+    loop {
+        let prompt = if source.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+
+        match editor.readline(prompt) {
+            Ok(line) => {
+                source.push_str(&line);
+                source.push('\n');
+            }
+            // Ctrl-C drops what was typed so far.
+            Err(ReadlineError::Interrupted) => {
+                source.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => return Ok(()),
+            Err(error) => return Err(readline_error(error)),
+        }
+
+        if is_incomplete(&source) {
+            continue;
+        }
+
+        let _ = editor.add_history_entry(source.trim_end());
+        match run_input(lox, &source, &mut io::stdout()) {
+            Ok(()) => (),
+            Err(LoxError::Exit(code)) => return Err(LoxError::Exit(code)),
+            // What the line defined before failing stays defined.
+            Err(error) => eprintln!("{error}"),
+        }
+        source.clear();
+
+        if let Some(helper) = editor.helper_mut() {
+            helper.names = lox.global_names();
+        }
+    }
+}
+
+// Runs a complete input, a command or code, writing what the session shows to `out`.
+pub fn run_input(lox: &mut Lox, source: &str, out: &mut impl Write) -> Result<(), LoxError> {
+    if source.starts_with(':') {
+        command(lox, source.trim(), out)
+    } else {
+        let value = eval(lox, source)?;
+        print_value(out, value)
+    }
+}
+
+fn command(lox: &mut Lox, line: &str, out: &mut impl Write) -> Result<(), LoxError> {
+    let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+    let argument = argument.trim();
+    let requires_argument = COMMANDS
+        .iter()
+        .any(|(usage, _)| usage.starts_with(&format!("{name} <")));
+    if requires_argument && argument.is_empty() {
+        return Err(LoxError::Usage(format!(
+            "Command '{name}' expects an argument."
+        )));
+    }
+
+    match name {
+        ":help" => {
+            for (usage, description) in COMMANDS {
+                writeln!(out, "  {usage:<16}{description}")?;
+            }
+        }
+        ":env" => {
+            for (name, value) in lox.globals() {
+                writeln!(out, "{name} = {}", value.repr())?;
+            }
+        }
+        ":type" => {
+            let value = lox.eval(&complete(argument)?)?;
+            writeln!(out, "{}", value.type_name())?;
+        }
+        ":ast" => print_ast(&complete(argument)?, out)?,
+        ":tokens" => print_tokens(argument, out)?,
+        ":load" => lox.run_file(argument)?,
+        ":reset" => *lox = cli_builder().build(),
+        ":time" => {
+            let start = Instant::now();
+            let value = eval(lox, argument);
+            let elapsed = start.elapsed();
+            print_value(out, value?)?;
+            writeln!(out, "Took {elapsed:?}.")?;
+        }
+        _ => {
+            return Err(LoxError::Usage(format!(
+                "Unknown command '{name}', see :help."
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn print_value(out: &mut impl Write, value: Option<Value>) -> Result<(), LoxError> {
+    if let Some(value) = value {
+        writeln!(out, "{}", value.repr())?;
+    }
+    Ok(())
+}
+
+// Returns the value of the input if it ends with an expression.
+fn eval(lox: &mut Lox, source: &str) -> Result<Option<Value>, LoxError> {
+    let source = complete(source)?;
+    let ends_with_expression = matches!(parse(&source).as_deref(), Ok([.., Stmt::Expression(_)]));
+    let value = lox.eval(&source)?;
+
+    // Calls and assignments evaluating to nil would be noise.
+    Ok(Some(value).filter(|value| ends_with_expression && !matches!(value, Value::Nil)))
+}
+
+// The `;` after a final expression is optional, `1 + 2` is read as `1 + 2;`.
+fn complete(source: &str) -> Result<String, LoxError> {
+    match parse(source) {
+        Ok(_) => Ok(source.to_string()),
+        Err(errors) => {
+            let completed = format!("{source};");
+            match parse(&completed) {
+                Ok(_) => Ok(completed),
+                Err(_) => Err(LoxError::Compile(errors)),
+            }
+        }
+    }
+}
+
+// Input is incomplete while a string or block comment is left open, or brackets of any
+// kind are. Other errors are left for the parser to report once the input is complete.
+// Commands always fit on one line.
+pub fn is_incomplete(source: &str) -> bool {
+    if source.starts_with(':') {
+        return false;
+    }
+
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens().to_vec();
+    let errors = take_syntax_errors().err().unwrap_or_default();
+
+    if errors
+        .iter()
+        .any(|error| error.message.starts_with("Unterminated"))
+    {
+        return true;
+    }
+
+    let mut depth = 0;
+    for token in &tokens {
+        match token.c_type {
+            TokenType::LeftParen | TokenType::LeftBrace | TokenType::LeftBracket => depth += 1,
+            TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket => depth -= 1,
+            _ => (),
+        }
+    }
+    depth > 0
+}
+
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+fn readline_error(error: ReadlineError) -> LoxError {
+    match error {
+        ReadlineError::Io(error) => LoxError::Io(error),
+        error => LoxError::Io(io::Error::other(error)),
+    }
+}
+
+// Completes keywords and the names of globals, and colors the input as it is typed.
+struct LoxHelper {
+    names: Vec<String>,
+    highlighter: highlighter::Highlighter,
+}
+
+impl Completer for LoxHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rfind(|(_, ch)| !(ch.is_alphanumeric() || *ch == '_'))
+            .map_or(0, |(index, ch)| index + ch.len_utf8());
+        let prefix = &line[start..pos];
+
+        if &line[..start] == ":" {
+            let commands = COMMANDS
+                .iter()
+                .filter_map(|(usage, _)| usage.split(' ').next())
+                .filter(|command| command[1..].starts_with(prefix))
+                .map(|command| command[1..].to_string())
+                .collect();
+            return Ok((start, commands));
+        }
+
+        // Properties and methods after a dot aren't known until the code runs.
+        if prefix.is_empty() || line[..start].ends_with('.') {
+            return Ok((pos, Vec::new()));
+        }
+
+        let mut candidates: Vec<String> = KEYWORDS
+            .iter()
+            .map(|(keyword, _)| *keyword)
+            .chain(self.names.iter().map(String::as_str))
+            .filter(|candidate| candidate.starts_with(prefix))
+            .map(str::to_string)
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for LoxHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LoxHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        if line.starts_with(':') {
+            return Cow::Borrowed(line);
+        }
+        Cow::Owned(self.highlighter.ansi(line))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, kind: CmdKind) -> bool {
+        kind != CmdKind::MoveCursor
+    }
+}
+
+impl Validator for LoxHelper {}
+
+impl Helper for LoxHelper {}
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+
+use crate::{
+    analysis::{Analysis, Declaration},
+    expression::Expr,
+    interpreter::Interpreter,
+    statement::{FunctionDecl, Stmt},
+    token::Token,
+    token_error,
+    token_type::TokenType,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+// Static pass run between parsing and interpreting. It tells the interpreter how many
+// scopes away every local variable was declared, and reports misuse like a top level `return`.
+pub struct Resolver<'i> {
+    interpreter: &'i mut Interpreter,
+    scopes: Vec<HashMap<String, Local>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    // Number of loops around the current statement, inside the current function.
+    loop_depth: usize,
+    // The symbols declared and where they are used, only kept for tools like the language
+    // server.
+    analysis: Option<Analysis>,
+}
+
+struct Local {
+    // Whether its initializer has finished resolving.
+    defined: bool,
+    // None for `this` and `super`, and when there is no analysis.
+    symbol: Option<usize>,
+}
+
+impl<'i> Resolver<'i> {
+    pub fn new(interpreter: &'i mut Interpreter) -> Self {
+        Self {
+            interpreter,
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            loop_depth: 0,
+            analysis: None,
+        }
+    }
+
+    // Also records the symbols of the source, see `into_analysis`.
+    pub fn with_analysis(interpreter: &'i mut Interpreter) -> Self {
+        Self {
+            analysis: Some(Analysis::default()),
+            ..Self::new(interpreter)
+        }
+    }
+
+    pub fn into_analysis(self) -> Analysis {
+        self.analysis.unwrap_or_default().finish()
+    }
+
+    pub fn resolve(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.resolve_stmt(statement);
+        }
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        self.see_line(stmt.line());
+
+        match stmt {
+            Stmt::Block(block) => {
+                self.begin_scope();
+                self.resolve(&block.statements);
+                self.end_scope();
+            }
+            Stmt::Break(keyword) => {
+                if self.loop_depth == 0 {
+                    token_error(keyword, "Can't use 'break' outside of a loop.");
+                }
+            }
+            Stmt::Class(class) => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Class;
+
+                let symbol = self.declare(&class.name, Declaration::Class(class), None);
+                self.define(&class.name);
+
+                if let Some(Expr::Variable(superclass)) = &class.superclass {
+                    if superclass.name.lexeme == class.name.lexeme {
+                        token_error(&superclass.name, "A class can't inherit from itself.");
+                    }
+                }
+
+                if let Some(superclass) = &class.superclass {
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expr(superclass);
+
+                    self.begin_scope();
+                    self.define_name("super");
+                }
+
+                self.begin_scope();
+                self.define_name("this");
+
+                for method in &class.methods {
+                    let is_init = method
+                        .name
+                        .as_ref()
+                        .is_some_and(|name| name.lexeme.as_ref() == "init");
+                    let declaration = if is_init {
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+                    // Methods aren't in any scope, they are found on the instance.
+                    let method_symbol = match (&mut self.analysis, &method.name) {
+                        (Some(analysis), Some(name)) => Some(analysis.declare(
+                            name,
+                            Declaration::Method(class, method),
+                            false,
+                            symbol,
+                        )),
+                        _ => None,
+                    };
+                    self.resolve_function(method, declaration, method_symbol);
+                }
+
+                self.end_scope();
+                if class.superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.current_class = enclosing_class;
+            }
+            Stmt::Export(declaration) => {
+                if !self.scopes.is_empty() {
+                    let name = match declaration.as_ref() {
+                        Stmt::Class(class) => Some(&class.name),
+                        Stmt::Function(function) => function.name.as_ref(),
+                        Stmt::Var(var_stmt) => Some(&var_stmt.name),
+                        _ => None,
+                    };
+                    if let Some(name) = name {
+                        token_error(name, "Can only export top-level declarations.");
+                    }
+                }
+                self.resolve_stmt(declaration);
+            }
+            Stmt::Expression(expr) => self.resolve_expr(expr),
+            Stmt::Print(print) => self.resolve_expr(&print.value),
+            Stmt::ForIn(for_in) => {
+                self.resolve_expr(&for_in.iterable);
+
+                // The loop variable lives in its own scope, fresh on every iteration.
+                self.begin_scope();
+                self.declare(&for_in.name, Declaration::Variable, None);
+                self.define(&for_in.name);
+                self.resolve_loop_body(&for_in.body);
+                self.end_scope();
+            }
+            Stmt::Function(function) => {
+                // Define eagerly so the function can refer to itself recursively.
+                let symbol = function.name.as_ref().and_then(|name| {
+                    let symbol = self.declare(name, Declaration::Function(function), None);
+                    self.define(name);
+                    symbol
+                });
+                self.resolve_function(function, FunctionType::Function, symbol);
+            }
+            Stmt::If(if_stmt) => {
+                self.resolve_expr(&if_stmt.condition);
+                self.resolve_stmt(&if_stmt.then_branch);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.resolve_stmt(else_branch);
+                }
+            }
+            Stmt::Import(import) => {
+                // Modules are loaded relative to the file running the import, which is only
+                // known while its top level code runs.
+                if !self.scopes.is_empty() {
+                    token_error(&import.keyword, "Can only import at the top level.");
+                }
+                for name in import.alias.iter().chain(&import.names) {
+                    self.declare(name, Declaration::Import(import), None);
+                }
+            }
+            Stmt::Return(return_stmt) => {
+                if self.current_function == FunctionType::None {
+                    token_error(&return_stmt.keyword, "Can't return from top-level code.");
+                }
+
+                if let Some(value) = &return_stmt.value {
+                    if self.current_function == FunctionType::Initializer {
+                        token_error(
+                            &return_stmt.keyword,
+                            "Can't return a value from an initializer.",
+                        );
+                    }
+                    self.resolve_expr(value);
+                }
+            }
+            Stmt::Throw(throw) => self.resolve_expr(&throw.value),
+            Stmt::Try(try_stmt) => {
+                self.begin_scope();
+                self.resolve(&try_stmt.body);
+                self.end_scope();
+
+                if let Some(catch) = &try_stmt.catch {
+                    self.begin_scope();
+                    self.declare(&catch.name, Declaration::CatchVariable, None);
+                    self.define(&catch.name);
+                    self.resolve(&catch.body);
+                    self.end_scope();
+                }
+
+                if let Some(finally) = &try_stmt.finally {
+                    self.begin_scope();
+                    self.resolve(finally);
+                    self.end_scope();
+                }
+            }
+            Stmt::Var(var_stmt) => {
+                self.declare(&var_stmt.name, Declaration::Variable, None);
+                if let Some(initializer) = &var_stmt.initializer {
+                    self.resolve_expr(initializer);
+                }
+                self.define(&var_stmt.name);
+            }
+            Stmt::While(while_stmt) => {
+                self.resolve_expr(&while_stmt.condition);
+                self.resolve_loop_body(&while_stmt.body);
+            }
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        self.see_line(expr.line());
+
+        match expr {
+            Expr::Assign(assign) => {
+                self.resolve_expr(&assign.value);
+                self.resolve_local(assign.id, &assign.name);
+            }
+            Expr::Binary(binary) => {
+                self.resolve_expr(&binary.left);
+                self.resolve_expr(&binary.right);
+            }
+            Expr::Call(call) => {
+                self.resolve_expr(&call.callee);
+                for argument in &call.arguments {
+                    self.resolve_expr(argument);
+                }
+                self.see_line(call.paren.line);
+            }
+            Expr::Get(get) => {
+                self.resolve_expr(&get.object);
+                self.property(&get.name);
+            }
+            Expr::Grouping(expr) => self.resolve_expr(expr),
+            Expr::Index(index) => {
+                self.resolve_expr(&index.object);
+                self.resolve_expr(&index.index);
+            }
+            Expr::Lambda(function) => self.resolve_function(function, FunctionType::Function, None),
+            Expr::List(list) => {
+                for element in &list.elements {
+                    self.resolve_expr(element);
+                }
+            }
+            Expr::Literal(_) => (),
+            Expr::Logical(logical) => {
+                self.resolve_expr(&logical.left);
+                self.resolve_expr(&logical.right);
+            }
+            Expr::Map(map) => {
+                for (key, value) in &map.entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            Expr::Set(set) => {
+                self.resolve_expr(&set.value);
+                self.resolve_expr(&set.object);
+                self.property(&set.name);
+            }
+            Expr::SetIndex(set_index) => {
+                self.resolve_expr(&set_index.value);
+                self.resolve_expr(&set_index.object);
+                self.resolve_expr(&set_index.index);
+            }
+            Expr::Super(super_expr) => {
+                match self.current_class {
+                    ClassType::None => {
+                        token_error(&super_expr.keyword, "Can't use 'super' outside of a class.")
+                    }
+                    ClassType::Class => token_error(
+                        &super_expr.keyword,
+                        "Can't use 'super' in a class with no superclass.",
+                    ),
+                    ClassType::Subclass => (),
+                }
+                self.resolve_local(super_expr.id, &super_expr.keyword);
+                self.property(&super_expr.method);
+            }
+            Expr::This(this) => {
+                if self.current_class == ClassType::None {
+                    token_error(&this.keyword, "Can't use 'this' outside of a class.");
+                    return;
+                }
+                self.resolve_local(this.id, &this.keyword);
+            }
+            Expr::Unary(unary) => self.resolve_expr(&unary.right),
+            Expr::Variable(variable) => {
+                let in_own_initializer = self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(variable.name.lexeme.as_ref()))
+                    .is_some_and(|local| !local.defined);
+                if in_own_initializer {
+                    token_error(
+                        &variable.name,
+                        "Can't read local variable in its own initializer.",
+                    );
+                }
+
+                self.resolve_local(variable.id, &variable.name);
+            }
+        }
+    }
+
+    fn resolve_loop_body(&mut self, body: &Stmt) {
+        self.loop_depth += 1;
+        self.resolve_stmt(body);
+        self.loop_depth -= 1;
+    }
+
+    // `symbol` is the function's, the parent of its parameters.
+    fn resolve_function(
+        &mut self,
+        function: &FunctionDecl,
+        function_type: FunctionType,
+        symbol: Option<usize>,
+    ) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+        // A `break` can't jump out of a function body into the loop around it.
+        let enclosing_loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param, Declaration::Parameter, symbol);
+            self.define(param);
+        }
+        self.resolve(&function.body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+        self.loop_depth = enclosing_loop_depth;
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        let scope = self.scopes.pop();
+        if let (Some(analysis), Some(scope)) = (&mut self.analysis, scope) {
+            analysis.end_scope(scope.into_values().filter_map(|local| local.symbol));
+        }
+    }
+
+    // Returns the symbol of the name when keeping an analysis.
+    fn declare(
+        &mut self,
+        name: &Token,
+        declaration: Declaration,
+        parent: Option<usize>,
+    ) -> Option<usize> {
+        let global = self.scopes.is_empty();
+        let symbol = self
+            .analysis
+            .as_mut()
+            .map(|analysis| analysis.declare(name, declaration, global, parent));
+
+        let Some(scope) = self.scopes.last_mut() else {
+            return symbol;
+        };
+
+        if scope.contains_key(name.lexeme.as_ref()) {
+            token_error(name, "Already a variable with this name in this scope.");
+        }
+        let local = Local {
+            defined: false,
+            symbol,
+        };
+        scope.insert(name.lexeme.to_string(), local);
+        symbol
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(local) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.get_mut(name.lexeme.as_ref()))
+        {
+            local.defined = true;
+        }
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            let local = Local {
+                defined: true,
+                symbol: None,
+            };
+            scope.insert(name.to_string(), local);
+        }
+    }
+
+    // Unresolved variables are assumed to be globals.
+    fn resolve_local(&mut self, id: usize, name: &Token) {
+        let mut symbol = None;
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(local) = scope.get(name.lexeme.as_ref()) {
+                self.interpreter.resolve(id, depth);
+                symbol = local.symbol;
+                break;
+            }
+        }
+
+        // `this` and `super` aren't names declared in the source.
+        if name.c_type == TokenType::Identifier {
+            if let Some(analysis) = &mut self.analysis {
+                analysis.reference(name, symbol);
+            }
+        }
+    }
+
+    fn property(&mut self, name: &Token) {
+        if let Some(analysis) = &mut self.analysis {
+            analysis.property(name);
+        }
+    }
+
+    fn see_line(&mut self, line: u32) {
+        if let Some(analysis) = &mut self.analysis {
+            analysis.see_line(line);
+        }
+    }
+}
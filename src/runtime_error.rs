@@ -0,0 +1,159 @@
+use std::{fmt, rc::Rc};
+
+use crate::{
+    token::{Span, Token},
+    value::Value,
+};
+
+// One line of a Lox level stack trace: the function and where it was executing.
+#[derive(Clone, PartialEq)]
+pub struct StackFrame {
+    pub(super) function: Rc<str>,
+    // None for code which wasn't read from a file, like in the REPL.
+    pub(super) file: Option<Rc<str>>,
+    pub(super) line: u32,
+}
+
+impl StackFrame {
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+}
+
+impl fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "at {} ({file}:{})", self.function, self.line),
+            None => write!(f, "at {} (line {})", self.function, self.line),
+        }
+    }
+}
+
+// The lines of a stack trace, innermost frame first. Runs of the same frame, as left by deep
+// recursion, are shown once followed by how many more there were.
+pub fn format_stack(stack: &[StackFrame]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut index = 0;
+    while index < stack.len() {
+        let frame = &stack[index];
+        let repeats = stack[index..]
+            .iter()
+            .take_while(|other| *other == frame)
+            .count();
+        lines.push(frame.to_string());
+        match repeats {
+            1 => (),
+            2 => lines.push(frame.to_string()),
+            repeats => lines.push(format!("... {} more frames", repeats - 1)),
+        }
+        index += repeats;
+    }
+    lines
+}
+
+pub struct RuntimeError {
+    // Only the line of the token the error was raised at is needed for reporting.
+    pub(super) line: u32,
+    // Where in the line, for tools which point at the code. Index errors cover the whole
+    // subscript.
+    pub(super) span: Span,
+    // A boxed str rather than a String keeps the error small, it travels through every
+    // Result of the tree walker.
+    pub(super) message: Box<str>,
+    // The value given to `throw`, None for errors raised by the interpreter itself.
+    pub(super) value: Option<Value>,
+    // Captured while the call frames are still around, innermost frame first.
+    pub(super) stack: Option<Vec<StackFrame>>,
+    // Exceeded execution limits can't be caught, or the script could just carry on.
+    pub(super) fatal: bool,
+}
+
+impl RuntimeError {
+    pub fn new(token: &Token, message: impl Into<String>) -> Self {
+        Self {
+            line: token.line,
+            span: token.span(),
+            message: message.into().into_boxed_str(),
+            value: None,
+            stack: None,
+            fatal: false,
+        }
+    }
+
+    pub fn fatal(token: &Token, message: impl Into<String>) -> Self {
+        Self {
+            fatal: true,
+            ..Self::new(token, message)
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        Self { span, ..self }
+    }
+
+    pub fn thrown(token: &Token, message: impl Into<String>, value: Value) -> Self {
+        Self {
+            value: Some(value),
+            ..Self::new(token, message)
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n[line {}]", self.message, self.line)?;
+
+        // A trace of the top level alone would only repeat the line.
+        if let Some(stack) = self.stack.as_ref().filter(|stack| stack.len() > 1) {
+            for line in format_stack(stack) {
+                write!(f, "\n  {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RuntimeError")
+            .field("message", &self.message)
+            .field("line", &self.line)
+            .finish()
+    }
+}
+
+// Everything that can unwind the Rust stack of the tree walker.
+// `Return` and `Break` are not errors, they just travel the same way up to the enclosing
+// call or loop. `Exit` comes from `os.exit` and can't be caught, it ends the script.
+pub enum Unwind {
+    Error(RuntimeError),
+    Return(Value),
+    Break,
+    Exit(i32),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Self {
+        Unwind::Error(error)
+    }
+}
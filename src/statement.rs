@@ -0,0 +1,242 @@
+use std::rc::Rc;
+
+use crate::{expression::Expr, token::Token};
+
+// Shared by function declarations, methods and lambdas.
+pub struct FunctionDecl {
+    // `fun`, the `(` of an arrow function or the name of a method, for the line.
+    pub(super) keyword: Token,
+    pub(super) name: Option<Token>, // None for lambdas
+    pub(super) params: Vec<Token>,
+    pub(super) body: Vec<Stmt>,
+}
+
+pub struct BlockStmt {
+    pub(super) brace: Token, // `for` for the blocks desugared from one
+    pub(super) statements: Vec<Stmt>,
+}
+
+pub struct ClassStmt {
+    pub(super) name: Token,
+    pub(super) superclass: Option<Expr>, // always an Expr::Variable
+    pub(super) methods: Vec<Rc<FunctionDecl>>,
+}
+
+pub struct ForInStmt {
+    pub(super) name: Token,
+    pub(super) keyword: Token, // `in`, used to report what can't be iterated
+    pub(super) iterable: Expr,
+    pub(super) body: Stmt,
+}
+
+pub struct IfStmt {
+    pub(super) keyword: Token,
+    pub(super) condition: Expr,
+    pub(super) then_branch: Stmt,
+    pub(super) else_branch: Option<Stmt>,
+}
+
+// `import "path" as alias;` or `import { names } from "path";`
+pub struct ImportStmt {
+    pub(super) keyword: Token,
+    pub(super) path: Token,
+    pub(super) alias: Option<Token>,
+    pub(super) names: Vec<Token>,
+}
+
+pub struct PrintStmt {
+    pub(super) keyword: Token,
+    pub(super) value: Expr,
+}
+
+pub struct ReturnStmt {
+    pub(super) keyword: Token,
+    pub(super) value: Option<Expr>,
+}
+
+pub struct ThrowStmt {
+    pub(super) keyword: Token,
+    pub(super) value: Expr,
+}
+
+pub struct CatchClause {
+    pub(super) name: Token,
+    pub(super) body: Vec<Stmt>,
+}
+
+pub struct TryStmt {
+    pub(super) keyword: Token,
+    pub(super) body: Vec<Stmt>,
+    pub(super) catch: Option<CatchClause>,
+    pub(super) finally: Option<Vec<Stmt>>,
+}
+
+pub struct VarStmt {
+    pub(super) name: Token,
+    pub(super) initializer: Option<Expr>,
+}
+
+pub struct WhileStmt {
+    pub(super) keyword: Token, // `for` for the loops desugared from one
+    pub(super) condition: Expr,
+    pub(super) body: Stmt,
+}
+
+impl FunctionDecl {
+    pub fn new(keyword: Token, name: Option<Token>, params: Vec<Token>, body: Vec<Stmt>) -> Self {
+        Self {
+            keyword,
+            name,
+            params,
+            body,
+        }
+    }
+}
+
+pub enum Stmt {
+    Block(Box<BlockStmt>),
+    Break(Token),
+    Class(Box<ClassStmt>),
+    Export(Box<Stmt>), // always wraps a class, function or var declaration
+    Expression(Expr),
+    ForIn(Box<ForInStmt>),
+    Function(Rc<FunctionDecl>),
+    If(Box<IfStmt>),
+    Import(Box<ImportStmt>),
+    Print(Box<PrintStmt>),
+    Return(Box<ReturnStmt>),
+    Throw(Box<ThrowStmt>),
+    Try(Box<TryStmt>),
+    Var(Box<VarStmt>),
+    While(Box<WhileStmt>),
+}
+
+impl Stmt {
+    // The line of a token kept in the tree for the statement.
+    pub fn line(&self) -> u32 {
+        match self {
+            Stmt::Block(block) => block.brace.line,
+            Stmt::Break(keyword) => keyword.line,
+            Stmt::Class(class) => class.name.line,
+            Stmt::Export(declaration) => declaration.line(),
+            Stmt::Expression(expr) => expr.line(),
+            Stmt::ForIn(for_in) => for_in.name.line,
+            Stmt::Function(function) => function.keyword.line,
+            Stmt::If(if_stmt) => if_stmt.keyword.line,
+            Stmt::Import(import) => import.keyword.line,
+            Stmt::Print(print) => print.keyword.line,
+            Stmt::Return(return_stmt) => return_stmt.keyword.line,
+            Stmt::Throw(throw) => throw.keyword.line,
+            Stmt::Try(try_stmt) => try_stmt.keyword.line,
+            Stmt::Var(var_stmt) => var_stmt.name.line,
+            Stmt::While(while_stmt) => while_stmt.keyword.line,
+        }
+    }
+
+    pub fn new_block(brace: Token, statements: Vec<Stmt>) -> Self {
+        Stmt::Block(Box::new(BlockStmt { brace, statements }))
+    }
+
+    pub fn new_break(keyword: Token) -> Self {
+        Stmt::Break(keyword)
+    }
+
+    pub fn new_class(
+        name: Token,
+        superclass: Option<Expr>,
+        methods: Vec<Rc<FunctionDecl>>,
+    ) -> Self {
+        Stmt::Class(Box::new(ClassStmt {
+            name,
+            superclass,
+            methods,
+        }))
+    }
+
+    pub fn new_export(declaration: Stmt) -> Self {
+        Stmt::Export(Box::new(declaration))
+    }
+
+    pub fn new_expression(expr: Expr) -> Self {
+        Stmt::Expression(expr)
+    }
+
+    pub fn new_for_in(name: Token, keyword: Token, iterable: Expr, body: Stmt) -> Self {
+        Stmt::ForIn(Box::new(ForInStmt {
+            name,
+            keyword,
+            iterable,
+            body,
+        }))
+    }
+
+    pub fn new_function(function: FunctionDecl) -> Self {
+        Stmt::Function(Rc::new(function))
+    }
+
+    pub fn new_if(
+        keyword: Token,
+        condition: Expr,
+        then_branch: Stmt,
+        else_branch: Option<Stmt>,
+    ) -> Self {
+        Stmt::If(Box::new(IfStmt {
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        }))
+    }
+
+    pub fn new_import(
+        keyword: Token,
+        path: Token,
+        alias: Option<Token>,
+        names: Vec<Token>,
+    ) -> Self {
+        Stmt::Import(Box::new(ImportStmt {
+            keyword,
+            path,
+            alias,
+            names,
+        }))
+    }
+
+    pub fn new_print(keyword: Token, value: Expr) -> Self {
+        Stmt::Print(Box::new(PrintStmt { keyword, value }))
+    }
+
+    pub fn new_return(keyword: Token, value: Option<Expr>) -> Self {
+        Stmt::Return(Box::new(ReturnStmt { keyword, value }))
+    }
+
+    pub fn new_throw(keyword: Token, value: Expr) -> Self {
+        Stmt::Throw(Box::new(ThrowStmt { keyword, value }))
+    }
+
+    pub fn new_try(
+        keyword: Token,
+        body: Vec<Stmt>,
+        catch: Option<CatchClause>,
+        finally: Option<Vec<Stmt>>,
+    ) -> Self {
+        Stmt::Try(Box::new(TryStmt {
+            keyword,
+            body,
+            catch,
+            finally,
+        }))
+    }
+
+    pub fn new_var(name: Token, initializer: Option<Expr>) -> Self {
+        Stmt::Var(Box::new(VarStmt { name, initializer }))
+    }
+
+    pub fn new_while(keyword: Token, condition: Expr, body: Stmt) -> Self {
+        Stmt::While(Box::new(WhileStmt {
+            keyword,
+            condition,
+            body,
+        }))
+    }
+}
@@ -0,0 +1,496 @@
+use std::{
+    cell::Cell,
+    env, fs,
+    io::{self, BufRead},
+    mem,
+    path::Path,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    capabilities::Capability,
+    environment::Environment,
+    interpreter::Interpreter,
+    lox_callable::NativeFunction,
+    lox_list::{self, ListRef},
+    lox_module::LoxModule,
+    runtime_error::{RuntimeError, Unwind},
+    token::Token,
+    value::Value,
+};
+
+// Defines the `math`, `string`, `time`, `io` and `os` modules as globals.
+pub fn define_modules(globals: &mut Environment) {
+    let modules = [
+        ("math", math_module()),
+        ("string", string_module()),
+        ("time", time_module()),
+        ("io", io_module()),
+        ("os", os_module()),
+    ];
+
+    for (name, members) in modules {
+        let module = LoxModule::native(name, members);
+        globals.define(name, Value::Module(Rc::new(module)));
+    }
+}
+
+fn native(
+    arity: usize,
+    function: impl Fn(&mut Interpreter, &Token, Vec<Value>) -> Result<Value, Unwind> + 'static,
+) -> Value {
+    Value::Native(Rc::new(NativeFunction::new(arity, function)))
+}
+
+// Wraps a numeric function of one argument, like `sqrt` or `sin`.
+fn unary_math(name: &'static str, function: fn(f64) -> f64) -> Value {
+    native(1, move |_, paren, arguments| {
+        let x = number(name, paren, &arguments, 0)?;
+        Ok(Value::Number(function(x)))
+    })
+}
+
+fn math_module() -> Vec<(&'static str, Value)> {
+    // xorshift64*, seeded from the clock until the script calls `seed`.
+    let state = Rc::new(Cell::new(initial_seed()));
+    let seed_state = Rc::clone(&state);
+
+    vec![
+        ("pi", Value::Number(std::f64::consts::PI)),
+        ("e", Value::Number(std::f64::consts::E)),
+        ("sqrt", unary_math("math.sqrt", f64::sqrt)),
+        ("floor", unary_math("math.floor", f64::floor)),
+        ("ceil", unary_math("math.ceil", f64::ceil)),
+        ("round", unary_math("math.round", f64::round)),
+        ("abs", unary_math("math.abs", f64::abs)),
+        ("sin", unary_math("math.sin", f64::sin)),
+        ("cos", unary_math("math.cos", f64::cos)),
+        ("tan", unary_math("math.tan", f64::tan)),
+        ("asin", unary_math("math.asin", f64::asin)),
+        ("acos", unary_math("math.acos", f64::acos)),
+        ("atan", unary_math("math.atan", f64::atan)),
+        ("exp", unary_math("math.exp", f64::exp)),
+        ("log", unary_math("math.log", f64::ln)),
+        (
+            "pow",
+            native(2, |_, paren, arguments| {
+                let base = number("math.pow", paren, &arguments, 0)?;
+                let exponent = number("math.pow", paren, &arguments, 1)?;
+                Ok(Value::Number(base.powf(exponent)))
+            }),
+        ),
+        (
+            "atan2",
+            native(2, |_, paren, arguments| {
+                let y = number("math.atan2", paren, &arguments, 0)?;
+                let x = number("math.atan2", paren, &arguments, 1)?;
+                Ok(Value::Number(y.atan2(x)))
+            }),
+        ),
+        (
+            "min",
+            native(2, |_, paren, arguments| {
+                let a = number("math.min", paren, &arguments, 0)?;
+                let b = number("math.min", paren, &arguments, 1)?;
+                Ok(Value::Number(a.min(b)))
+            }),
+        ),
+        (
+            "max",
+            native(2, |_, paren, arguments| {
+                let a = number("math.max", paren, &arguments, 0)?;
+                let b = number("math.max", paren, &arguments, 1)?;
+                Ok(Value::Number(a.max(b)))
+            }),
+        ),
+        (
+            "random",
+            native(0, move |_, _, _| {
+                let mut x = state.get();
+                x ^= x >> 12;
+                x ^= x << 25;
+                x ^= x >> 27;
+                state.set(x);
+                // The top 53 bits give a uniform float in [0, 1).
+                let bits = x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+                Ok(Value::Number(bits as f64 / (1u64 << 53) as f64))
+            }),
+        ),
+        (
+            "seed",
+            native(1, move |_, paren, arguments| {
+                let seed = integer("math.seed", paren, &arguments, 0)?;
+                // xorshift gets stuck on an all zero state.
+                seed_state.set((seed as u64) ^ 0x9e37_79b9_7f4a_7c15);
+                Ok(Value::Nil)
+            }),
+        ),
+    ]
+}
+
+fn initial_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64);
+    nanos | 1
+}
+
+fn string_module() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "len",
+            native(1, |_, paren, arguments| {
+                let s = string("string.len", paren, &arguments, 0)?;
+                Ok(Value::Number(s.chars().count() as f64))
+            }),
+        ),
+        (
+            "split",
+            native(2, |interpreter, paren, arguments| {
+                let s = string("string.split", paren, &arguments, 0)?;
+                let separator = string("string.split", paren, &arguments, 1)?;
+                // An empty separator splits the string into its characters.
+                let parts: Vec<Value> = if separator.is_empty() {
+                    s.chars()
+                        .map(|c| Value::Str(c.to_string().into()))
+                        .collect()
+                } else {
+                    s.split(separator.as_ref())
+                        .map(|part| Value::Str(part.into()))
+                        .collect()
+                };
+                interpreter.allocate(s.len() + parts.len() * mem::size_of::<Value>())?;
+                Ok(lox_list::new_list(parts))
+            }),
+        ),
+        (
+            "join",
+            native(2, |interpreter, paren, arguments| {
+                let list = list("string.join", paren, &arguments, 0)?;
+                let separator = string("string.join", paren, &arguments, 1)?;
+                let parts: Vec<String> = list.borrow().iter().map(Value::to_string).collect();
+                let joined = parts.join(&separator);
+                interpreter.allocate(joined.len())?;
+                Ok(Value::Str(joined.into()))
+            }),
+        ),
+        (
+            "trim",
+            native(1, |_, paren, arguments| {
+                let s = string("string.trim", paren, &arguments, 0)?;
+                Ok(Value::Str(s.trim().into()))
+            }),
+        ),
+        (
+            "upper",
+            native(1, |_, paren, arguments| {
+                let s = string("string.upper", paren, &arguments, 0)?;
+                Ok(Value::Str(s.to_uppercase().into()))
+            }),
+        ),
+        (
+            "lower",
+            native(1, |_, paren, arguments| {
+                let s = string("string.lower", paren, &arguments, 0)?;
+                Ok(Value::Str(s.to_lowercase().into()))
+            }),
+        ),
+        (
+            "replace",
+            native(3, |interpreter, paren, arguments| {
+                let s = string("string.replace", paren, &arguments, 0)?;
+                let from = string("string.replace", paren, &arguments, 1)?;
+                let to = string("string.replace", paren, &arguments, 2)?;
+                if from.is_empty() {
+                    return Err(RuntimeError::new(paren, "Can't replace an empty string.").into());
+                }
+                let replaced = s.replace(from.as_ref(), &to);
+                interpreter.allocate(replaced.len())?;
+                Ok(Value::Str(replaced.into()))
+            }),
+        ),
+        (
+            "find",
+            native(2, |_, paren, arguments| {
+                let s = string("string.find", paren, &arguments, 0)?;
+                let needle = string("string.find", paren, &arguments, 1)?;
+                // Indices are in characters, like `substring`, and -1 means not found.
+                let index = s
+                    .find(needle.as_ref())
+                    .map_or(-1.0, |byte| s[..byte].chars().count() as f64);
+                Ok(Value::Number(index))
+            }),
+        ),
+        (
+            "substring",
+            native(3, |_, paren, arguments| {
+                let s = string("string.substring", paren, &arguments, 0)?;
+                let len = s.chars().count();
+                let start = lox_list::check_index(paren, &arguments[1], len, true)?;
+                let end = lox_list::check_index(paren, &arguments[2], len, true)?;
+                if start > end {
+                    return Err(RuntimeError::new(
+                        paren,
+                        format!("Substring start {start} is after end {end}."),
+                    )
+                    .into());
+                }
+                let substring: String = s.chars().skip(start).take(end - start).collect();
+                Ok(Value::Str(substring.into()))
+            }),
+        ),
+    ]
+}
+
+fn time_module() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "now",
+            native(0, |interpreter, paren, _| {
+                interpreter.capabilities().check(paren, Capability::Clock)?;
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0.0, |duration| duration.as_secs_f64());
+                Ok(Value::Number(now))
+            }),
+        ),
+        (
+            "sleep",
+            native(1, |interpreter, paren, arguments| {
+                interpreter.capabilities().check(paren, Capability::Clock)?;
+                let seconds = number("time.sleep", paren, &arguments, 0)?;
+                // Negative, not a number or too long for a Duration.
+                let Ok(duration) = Duration::try_from_secs_f64(seconds) else {
+                    return Err(RuntimeError::new(
+                        paren,
+                        format!("Can't sleep for {seconds} seconds."),
+                    )
+                    .into());
+                };
+                interpreter.sleep(duration)?;
+                Ok(Value::Nil)
+            }),
+        ),
+        (
+            "format",
+            native(2, |_, paren, arguments| {
+                let timestamp = number("time.format", paren, &arguments, 0)?;
+                let format = string("time.format", paren, &arguments, 1)?;
+                Ok(Value::Str(format_time(paren, timestamp, &format)?.into()))
+            }),
+        ),
+    ]
+}
+
+// A small strftime, always in UTC: %Y %m %d %H %M %S and %%.
+fn format_time(paren: &Token, timestamp: f64, format: &str) -> Result<String, RuntimeError> {
+    let seconds = timestamp.floor() as i64;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let second_of_day = seconds.rem_euclid(86_400);
+
+    let mut formatted = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            formatted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => formatted.push_str(&format!("{year:04}")),
+            Some('m') => formatted.push_str(&format!("{month:02}")),
+            Some('d') => formatted.push_str(&format!("{day:02}")),
+            Some('H') => formatted.push_str(&format!("{:02}", second_of_day / 3600)),
+            Some('M') => formatted.push_str(&format!("{:02}", second_of_day / 60 % 60)),
+            Some('S') => formatted.push_str(&format!("{:02}", second_of_day % 60)),
+            Some('%') => formatted.push('%'),
+            Some(other) => {
+                return Err(RuntimeError::new(
+                    paren,
+                    format!("Unknown time format directive '%{other}'."),
+                ))
+            }
+            None => {
+                return Err(RuntimeError::new(
+                    paren,
+                    "Time format can't end with a lone '%'.",
+                ))
+            }
+        }
+    }
+    Ok(formatted)
+}
+
+// Days since 1970-01-01 to a (year, month, day) date, from Howard Hinnant's date algorithms.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn io_module() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "readFile",
+            native(1, |interpreter, paren, arguments| {
+                let path = string("io.readFile", paren, &arguments, 0)?;
+                interpreter
+                    .capabilities()
+                    .check(paren, Capability::FsRead(Path::new(path.as_ref())))?;
+                let contents = fs::read_to_string(path.as_ref()).map_err(|error| {
+                    RuntimeError::new(paren, format!("Can't read file '{path}': {error}."))
+                })?;
+                Ok(Value::Str(contents.into()))
+            }),
+        ),
+        (
+            "writeFile",
+            native(2, |interpreter, paren, arguments| {
+                let path = string("io.writeFile", paren, &arguments, 0)?;
+                let contents = string("io.writeFile", paren, &arguments, 1)?;
+                interpreter
+                    .capabilities()
+                    .check(paren, Capability::FsWrite(Path::new(path.as_ref())))?;
+                fs::write(path.as_ref(), contents.as_bytes()).map_err(|error| {
+                    RuntimeError::new(paren, format!("Can't write file '{path}': {error}."))
+                })?;
+                Ok(Value::Nil)
+            }),
+        ),
+        (
+            "readLine",
+            native(0, |interpreter, paren, _| {
+                interpreter.capabilities().check(paren, Capability::Stdin)?;
+                // A prompt printed just before should show up before waiting for input.
+                interpreter.flush_output().ok();
+                let mut line = String::new();
+                let bytes_read = io::stdin().lock().read_line(&mut line).map_err(|error| {
+                    RuntimeError::new(paren, format!("Can't read from stdin: {error}."))
+                })?;
+
+                // nil at the end of input, so loops can tell it apart from an empty line.
+                if bytes_read == 0 {
+                    return Ok(Value::Nil);
+                }
+                let line = line.strip_suffix('\n').unwrap_or(&line);
+                let line = line.strip_suffix('\r').unwrap_or(line);
+                Ok(Value::Str(line.into()))
+            }),
+        ),
+    ]
+}
+
+fn os_module() -> Vec<(&'static str, Value)> {
+    vec![
+        (
+            "args",
+            native(0, |interpreter, _, _| {
+                let args = interpreter
+                    .args()
+                    .iter()
+                    .map(|arg| Value::Str(arg.as_str().into()))
+                    .collect();
+                Ok(lox_list::new_list(args))
+            }),
+        ),
+        (
+            "env",
+            native(1, |interpreter, paren, arguments| {
+                interpreter.capabilities().check(paren, Capability::Env)?;
+                let name = string("os.env", paren, &arguments, 0)?;
+                Ok(env::var(name.as_ref()).map_or(Value::Nil, |value| Value::Str(value.into())))
+            }),
+        ),
+        (
+            "exit",
+            native(1, |interpreter, paren, arguments| {
+                interpreter.capabilities().check(paren, Capability::Exit)?;
+                let code = integer("os.exit", paren, &arguments, 0)?;
+                let code = i32::try_from(code).map_err(|_| {
+                    RuntimeError::new(paren, format!("Exit code {code} is out of range."))
+                })?;
+                Err(Unwind::Exit(code))
+            }),
+        ),
+    ]
+}
+
+fn type_error(
+    function: &str,
+    paren: &Token,
+    index: usize,
+    expected: &str,
+    got: &Value,
+) -> RuntimeError {
+    RuntimeError::new(
+        paren,
+        format!(
+            "{function}() expects a {expected} as argument {}, got {}.",
+            index + 1,
+            got.type_name()
+        ),
+    )
+}
+
+fn number(
+    function: &str,
+    paren: &Token,
+    arguments: &[Value],
+    index: usize,
+) -> Result<f64, RuntimeError> {
+    match &arguments[index] {
+        Value::Number(n) => Ok(*n),
+        other => Err(type_error(function, paren, index, "number", other)),
+    }
+}
+
+fn integer(
+    function: &str,
+    paren: &Token,
+    arguments: &[Value],
+    index: usize,
+) -> Result<i64, RuntimeError> {
+    let n = number(function, paren, arguments, index)?;
+    if n.fract() != 0.0 {
+        return Err(RuntimeError::new(
+            paren,
+            format!(
+                "{function}() expects an integer as argument {}, got {n}.",
+                index + 1
+            ),
+        ));
+    }
+    Ok(n as i64)
+}
+
+fn string(
+    function: &str,
+    paren: &Token,
+    arguments: &[Value],
+    index: usize,
+) -> Result<Rc<str>, RuntimeError> {
+    match &arguments[index] {
+        Value::Str(s) => Ok(Rc::clone(s)),
+        other => Err(type_error(function, paren, index, "string", other)),
+    }
+}
+
+fn list(
+    function: &str,
+    paren: &Token,
+    arguments: &[Value],
+    index: usize,
+) -> Result<ListRef, RuntimeError> {
+    match &arguments[index] {
+        Value::List(list) => Ok(Rc::clone(list)),
+        other => Err(type_error(function, paren, index, "list", other)),
+    }
+}
@@ -1,16 +1,19 @@
-use std::fmt::{self, Debug};
+use std::{
+    fmt::{self, Debug},
+    rc::Rc,
+};
 
 use crate::token_type::TokenType;
 
-#[derive(Clone, Copy)]
-pub enum Literal<'a> {
-    Str(&'a str),
+#[derive(Clone)]
+pub enum Literal {
+    Str(Rc<str>),
     Num(f64),
     Bool(bool),
     Nil,
 }
 
-impl<'a> fmt::Display for Literal<'a> {
+impl fmt::Display for Literal {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Literal::Str(s) => write!(f, "{}", s),
@@ -22,7 +25,7 @@ impl<'a> fmt::Display for Literal<'a> {
 }
 
 // Though we could use default Debug.
-impl<'a> Debug for Literal<'a> {
+impl Debug for Literal {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Literal::Str(s) => write!(f, "{}", s),
@@ -33,33 +36,66 @@ impl<'a> Debug for Literal<'a> {
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct Token<'a> {
+// Tokens own their lexeme so the AST (and the functions and classes created from it)
+// can outlive the source string they were scanned from.
+#[derive(Clone)]
+pub struct Token {
     pub(super) c_type: TokenType, // type is reserved so c_type, c for custom
-    pub(super) lexeme: &'a str,
-    pub(super) literal: Option<Literal<'a>>,
+    pub(super) lexeme: Rc<str>,
+    pub(super) literal: Option<Literal>,
     pub(super) line: u32,
+    pub(super) column: u32, // of the first character, counting from 1, 0 for made up tokens
 }
 
-impl<'a> Token<'a> {
-    pub fn new(
-        c_type: TokenType,
-        lexeme: &'a str,
-        literal: Option<Literal<'a>>,
-        line: u32,
-    ) -> Self {
+impl Token {
+    pub fn new(c_type: TokenType, lexeme: &str, literal: Option<Literal>, line: u32) -> Self {
         Token {
             c_type,
-            lexeme,
+            lexeme: Rc::from(lexeme),
             literal,
             line,
+            column: 0,
         }
     }
+
+    pub fn span(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.column,
+            end_column: self.column + self.lexeme.chars().count() as u32,
+        }
+    }
+
+    // From the start of this token to the end of `end`, or just this token when `end` is on
+    // a later line, as spans don't cross lines.
+    pub fn span_to(&self, end: &Token) -> Span {
+        if end.line != self.line {
+            return self.span();
+        }
+        Span {
+            end_column: end.span().end_column,
+            ..self.span()
+        }
+    }
+}
+
+// Where a token is in the source, columns count from 1 and the end is exclusive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub line: u32,
+    pub column: u32,
+    pub end_column: u32,
+}
+
+impl Span {
+    pub fn contains(&self, line: u32, column: u32) -> bool {
+        self.line == line && self.column <= column && column < self.end_column
+    }
 }
 
-impl<'a> fmt::Display for Token<'a> {
+impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.literal {
+        match &self.literal {
             Some(literal) => write!(f, "{:?} {} {:?}", self.c_type, self.lexeme, literal),
             None => write!(f, "{:?} {}", self.c_type, self.lexeme),
         }
@@ -67,9 +103,9 @@ impl<'a> fmt::Display for Token<'a> {
 }
 
 // Though we could use default Debug.
-impl<'a> Debug for Token<'a> {
+impl Debug for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.literal {
+        match &self.literal {
             Some(literal) => write!(f, "{:?} {} {:?}", self.c_type, self.lexeme, literal),
             None => write!(f, "{:?} {}", self.c_type, self.lexeme),
         }
@@ -1,23 +1,27 @@
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum TokenType {
     // Single-character tokens.
-    LeftParen,  // (
-    RightParen, // )
-    LeftBrace,  // {
-    RightBrace, // }
-    Comma,      // ,
-    Dot,        // .
-    Minus,      // -
-    Plus,       // +
-    SemiColon,  // ;
-    Slash,      // /
-    Star,       // *
+    LeftParen,    // (
+    RightParen,   // )
+    LeftBrace,    // {
+    RightBrace,   // }
+    LeftBracket,  // [
+    RightBracket, // ]
+    Colon,        // :
+    Comma,        // ,
+    Dot,          // .
+    Minus,        // -
+    Plus,         // +
+    SemiColon,    // ;
+    Slash,        // /
+    Star,         // *
 
     // One or two character tokens.
     Bang,         // !
     BangEqual,    // !=
     Equal,        // =
     EqualEqual,   // ==
+    Arrow,        // =>
     Greater,      // >
     GreaterEqual, // >=
     Less,         // <
@@ -30,21 +34,32 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Break,
+    Catch,
     Class,
     Else,
+    Export,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
+    In,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
+    // Only produced by `Scanner::with_comments`, for tools which work on the source text.
+    Comment,
+
     Eof,
 }
@@ -0,0 +1,160 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    lox_callable::NativeFunction, lox_class::LoxClass, lox_function::LoxFunction,
+    lox_instance::LoxInstance, lox_list::ListRef, lox_map::MapRef, lox_module::LoxModule,
+    token::Literal,
+};
+
+// Runtime representation of every Lox value. Cloning is cheap, objects are reference counted.
+#[derive(Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Str(Rc<str>),
+    Function(Rc<LoxFunction>),
+    Native(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    List(ListRef),
+    Map(MapRef),
+    Module(Rc<LoxModule>),
+    // Half open range of numbers, `range(start, end)`, iterated one step at a time.
+    Range(f64, f64),
+}
+
+impl Value {
+    // false and nil are falsey, and everything else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Nil => false,
+            Value::Bool(b) => *b,
+            _ => true,
+        }
+    }
+
+    pub fn is_equal(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
+            (Value::Range(a_start, a_end), Value::Range(b_start, b_end)) => {
+                a_start == b_start && a_end == b_end
+            }
+            _ => false,
+        }
+    }
+
+    // Used by natives to report arguments of the wrong type.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "bool",
+            Value::Number(_) => "number",
+            Value::Str(_) => "string",
+            Value::Function(_) | Value::Native(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Module(_) => "module",
+            Value::Range(..) => "range",
+        }
+    }
+
+    // How the REPL echoes a value, strings are quoted to tell `"1"` from `1`.
+    pub fn repr(&self) -> String {
+        match self {
+            Value::Str(s) => format!("\"{s}\""),
+            value => value.to_string(),
+        }
+    }
+
+    // Quote strings inside collections so `["a, b"]` and `["a", "b"]` print differently.
+    fn fmt_nested(&self, f: &mut fmt::Formatter<'_>, printing: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Value::Str(s) => write!(f, "\"{s}\""),
+            value => value.fmt_with(f, printing),
+        }
+    }
+
+    // `printing` has the lists and maps being written around this value, one which contains
+    // itself is written as `[...]` or `{...}` the second time instead of recursing forever.
+    fn fmt_with(&self, f: &mut fmt::Formatter<'_>, printing: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Value::List(list) => {
+                let id = Rc::as_ptr(list).cast::<()>();
+                if printing.contains(&id) {
+                    return write!(f, "[...]");
+                }
+                printing.push(id);
+                write!(f, "[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.fmt_nested(f, printing)?;
+                }
+                printing.pop();
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                let id = Rc::as_ptr(map).cast::<()>();
+                if printing.contains(&id) {
+                    return write!(f, "{{...}}");
+                }
+                printing.push(id);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    key.fmt_nested(f, printing)?;
+                    write!(f, ": ")?;
+                    value.fmt_nested(f, printing)?;
+                }
+                printing.pop();
+                write!(f, "}}")
+            }
+            value => write!(f, "{value}"),
+        }
+    }
+}
+
+impl From<Literal> for Value {
+    fn from(literal: Literal) -> Self {
+        match literal {
+            Literal::Str(s) => Value::Str(s),
+            Literal::Num(n) => Value::Number(n),
+            Literal::Bool(b) => Value::Bool(b),
+            Literal::Nil => Value::Nil,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Str(s) => write!(f, "{s}"),
+            Value::Function(function) => write!(f, "{function}"),
+            Value::Native(_) => write!(f, "<native fn>"),
+            Value::Class(class) => write!(f, "{}", class.name),
+            Value::Instance(instance) => write!(f, "{} instance", instance.borrow().class.name),
+            Value::List(_) | Value::Map(_) => self.fmt_with(f, &mut Vec::new()),
+            Value::Module(module) => write!(f, "{module}"),
+            Value::Range(start, end) => write!(f, "<range {start}..{end}>"),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+// Scripts run through the embedding API get no I/O unless the host allows it, and denied
+// operations raise a runtime error naming the capability.
+
+use tree_walk_interpreter::{capabilities::Capabilities, Lox, LoxError};
+
+fn error_message(lox: &mut Lox, source: &str) -> String {
+    match lox.eval(source) {
+        Err(LoxError::Runtime(error)) => error.message().to_string(),
+        Err(error) => panic!("expected a runtime error, got {error}"),
+        Ok(value) => panic!("expected a runtime error, got {value}"),
+    }
+}
+
+fn with(capabilities: Capabilities) -> Lox {
+    Lox::builder().capabilities(capabilities).build()
+}
+
+#[test]
+fn denied_by_default() {
+    let mut lox = Lox::new();
+    let denied = [
+        (
+            "io.readFile(\"Cargo.toml\");",
+            "Capability 'fs.read' denied for 'Cargo.toml'.",
+        ),
+        (
+            "io.writeFile(\"out.txt\", \"x\");",
+            "Capability 'fs.write' denied for 'out.txt'.",
+        ),
+        ("os.env(\"HOME\");", "Capability 'env' denied."),
+        ("os.exit(1);", "Capability 'exit' denied."),
+        ("io.readLine();", "Capability 'stdin' denied."),
+        ("time.now();", "Capability 'clock' denied."),
+        ("clock();", "Capability 'clock' denied."),
+    ];
+    for (source, message) in denied {
+        assert_eq!(error_message(&mut lox, source), message, "{source}");
+    }
+}
+
+#[test]
+fn denied_errors_can_be_caught() {
+    let mut lox = Lox::new();
+    let value = lox
+        .eval("var message; try { os.env(\"HOME\"); } catch (e) { message = e.message; } message;")
+        .unwrap();
+    assert_eq!(value.to_string(), "Capability 'env' denied.");
+}
+
+#[test]
+fn reads_only_under_allowed_roots() {
+    let mut lox = with(Capabilities::none().allow_read("tests/lox/stdlib"));
+    let value = lox
+        .eval("string.len(io.readFile(\"tests/lox/stdlib/math.lox\")) > 0;")
+        .unwrap();
+    assert_eq!(value.to_string(), "true");
+
+    assert_eq!(
+        error_message(&mut lox, "io.readFile(\"Cargo.toml\");"),
+        "Capability 'fs.read' denied for 'Cargo.toml'."
+    );
+    // `..` can't climb out of a root.
+    assert_eq!(
+        error_message(
+            &mut lox,
+            "io.readFile(\"tests/lox/stdlib/../../../Cargo.toml\");"
+        ),
+        "Capability 'fs.read' denied for 'tests/lox/stdlib/../../../Cargo.toml'."
+    );
+    // Reading doesn't allow writing.
+    assert_eq!(
+        error_message(
+            &mut lox,
+            "io.writeFile(\"tests/lox/stdlib/out.txt\", \"\");"
+        ),
+        "Capability 'fs.write' denied for 'tests/lox/stdlib/out.txt'."
+    );
+}
+
+#[test]
+fn imports_need_read_access() {
+    let mut lox = Lox::new();
+    let message = error_message(
+        &mut lox,
+        "import \"tests/lox/modules/lib/shapes.lox\" as m;",
+    );
+    // Modules are resolved to an absolute path before they are checked.
+    assert!(
+        message.starts_with("Capability 'fs.read' denied for '/")
+            && message.ends_with("tests/lox/modules/lib/shapes.lox'."),
+        "{message}"
+    );
+}
+
+#[test]
+fn allowed_capabilities() {
+    let mut lox = with(Capabilities::none().allow_env().allow_clock().allow_exit());
+    assert_eq!(
+        lox.eval("os.env(\"LOX_TEST_SURELY_UNSET\");")
+            .unwrap()
+            .to_string(),
+        "nil"
+    );
+    assert_eq!(lox.eval("time.now() > 0;").unwrap().to_string(), "true");
+    assert!(matches!(lox.eval("os.exit(3);"), Err(LoxError::Exit(3))));
+    // Still nothing else.
+    assert_eq!(
+        error_message(&mut lox, "io.readLine();"),
+        "Capability 'stdin' denied."
+    );
+}
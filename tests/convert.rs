@@ -0,0 +1,113 @@
+// Rust values passed through Lox and back keep their value, and values of the wrong shape
+// say what was expected.
+
+use std::collections::{BTreeMap, HashMap};
+
+use tree_walk_interpreter::{FromLox, IntoLox, Lox, Value};
+
+fn round_trip<T: IntoLox + FromLox + Clone>(value: T) -> T {
+    let mut lox = Lox::new();
+    lox.define_global("value", value);
+    let copy = lox.eval("value;").unwrap();
+    T::from_lox(&copy).unwrap()
+}
+
+fn error<T: FromLox>(lox: &mut Lox, source: &str) -> String {
+    let value = lox.eval(source).unwrap();
+    match T::from_lox(&value) {
+        Ok(_) => panic!("expected {source} not to convert"),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[test]
+fn round_trips() {
+    assert!(round_trip(true));
+    assert_eq!(round_trip(1.5), 1.5);
+    assert_eq!(round_trip(-7i32), -7);
+    assert_eq!(round_trip(u64::from(u32::MAX)), u64::from(u32::MAX));
+    assert_eq!(round_trip("héllo".to_string()), "héllo");
+    assert_eq!(round_trip(Some(3.0)), Some(3.0));
+    assert_eq!(round_trip(None::<f64>), None);
+    assert_eq!(round_trip(vec![1, 2, 3]), vec![1, 2, 3]);
+    assert_eq!(
+        round_trip(("a".to_string(), 2, false)),
+        ("a".to_string(), 2, false)
+    );
+    let map = HashMap::from([("a".to_string(), 1.0), ("b".to_string(), 2.0)]);
+    assert_eq!(round_trip(map.clone()), map);
+}
+
+#[test]
+fn maps_keep_their_keys_sorted() {
+    let mut lox = Lox::new();
+    let map = BTreeMap::from([("b".to_string(), 2), ("a".to_string(), 1)]);
+    lox.define_global("map", map);
+    assert_eq!(lox.eval("map;").unwrap().to_string(), r#"{"a": 1, "b": 2}"#);
+}
+
+#[test]
+fn conversion_errors() {
+    let mut lox = Lox::new();
+    assert_eq!(
+        error::<f64>(&mut lox, "\"1\";"),
+        "Expected a number, got string."
+    );
+    assert_eq!(error::<bool>(&mut lox, "nil;"), "Expected a bool, got nil.");
+    assert_eq!(
+        error::<u8>(&mut lox, "256;"),
+        "Expected an integer that fits in u8, got 256."
+    );
+    assert_eq!(
+        error::<i32>(&mut lox, "1.5;"),
+        "Expected an integer that fits in i32, got 1.5."
+    );
+    assert_eq!(
+        error::<Vec<String>>(&mut lox, "[\"a\", 1];"),
+        "Expected a string, got number."
+    );
+    assert_eq!(
+        error::<HashMap<String, f64>>(&mut lox, "[];"),
+        "Expected a map, got list."
+    );
+}
+
+#[test]
+fn integer_bounds() {
+    // 2^63 and 2^64, one past the largest i64 and u64.
+    assert!(i64::from_lox(&Value::Number(9223372036854775808.0)).is_err());
+    assert!(u64::from_lox(&Value::Number(18446744073709551616.0)).is_err());
+    assert_eq!(
+        i64::from_lox(&Value::Number(-9223372036854775808.0)).unwrap(),
+        i64::MIN
+    );
+    assert!(i32::from_lox(&Value::Number(2147483648.0)).is_err());
+    assert_eq!(
+        i32::from_lox(&Value::Number(2147483647.0)).unwrap(),
+        i32::MAX
+    );
+    assert!(u8::from_lox(&Value::Number(-1.0)).is_err());
+}
+
+#[test]
+fn tuple_length_mismatch() {
+    let mut lox = Lox::new();
+    assert_eq!(
+        error::<(f64, f64)>(&mut lox, "[1, 2, 3];"),
+        "Expected a list of length 2, got a list of length 3."
+    );
+    assert_eq!(
+        error::<(f64,)>(&mut lox, "1;"),
+        "Expected a list of length 1, got number."
+    );
+}
+
+#[test]
+fn get_converts_globals() {
+    let mut lox = Lox::new();
+    lox.eval("var answer = 42; var name = \"lox\";").unwrap();
+    assert_eq!(lox.get::<i64>("answer").unwrap().unwrap(), 42);
+    assert!(lox.get::<i64>("name").unwrap().is_err());
+    assert!(lox.get::<i64>("missing").is_none());
+    assert!(matches!(Value::from_lox(&Value::Nil), Ok(Value::Nil)));
+}
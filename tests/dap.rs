@@ -0,0 +1,112 @@
+// Replays the recorded debug sessions in tests/dap/*.dap against `lox dap`, checking every
+// message it sends back. Each session debugs the .lox script with the same name.
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use serde_json::Value;
+
+// Objects only need the expected fields, arrays all of their elements.
+fn matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected
+            .iter()
+            .all(|(key, value)| actual.get(key).is_some_and(|actual| matches(value, actual))),
+        (Value::Array(expected), Value::Array(actual)) => {
+            expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .zip(actual)
+                    .all(|(expected, actual)| matches(expected, actual))
+        }
+        (expected, actual) => expected == actual,
+    }
+}
+
+fn read_messages(output: &[u8]) -> Vec<Value> {
+    let mut reader = BufReader::new(output);
+    let mut messages = Vec::new();
+    loop {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).unwrap() == 0 {
+                return messages;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = Some(value.parse().unwrap());
+            }
+        }
+        let mut body = vec![0; length.expect("a Content-Length header")];
+        reader.read_exact(&mut body).unwrap();
+        messages.push(serde_json::from_slice(&body).unwrap());
+    }
+}
+
+fn replay(transcript: &Path) {
+    let program = transcript.with_extension("lox");
+    let transcript = fs::read_to_string(transcript)
+        .unwrap()
+        .replace("${program}", &program.display().to_string());
+
+    let mut requests = Vec::new();
+    let mut expected = Vec::new();
+    for line in transcript.lines() {
+        if let Some(request) = line.strip_prefix("--> ") {
+            requests.push(serde_json::from_str::<Value>(request).unwrap());
+        } else if let Some(message) = line.strip_prefix("<-- ") {
+            expected.push(serde_json::from_str::<Value>(message).unwrap());
+        }
+    }
+
+    // The adapter reads requests as it needs them, so they can all be sent up front.
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tree-walk-interpreter"))
+        .arg("dap")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("the interpreter should start");
+    let mut stdin = child.stdin.take().unwrap();
+    for request in &requests {
+        let body = request.to_string();
+        write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    }
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    let messages = read_messages(&output.stdout);
+    for (index, expected) in expected.iter().enumerate() {
+        let actual = messages.get(index).unwrap_or(&Value::Null);
+        assert!(
+            matches(expected, actual),
+            "message {}:\n  expected {expected}\n  got      {actual}",
+            index + 1
+        );
+    }
+    assert_eq!(messages.len(), expected.len(), "got {messages:#?}");
+    assert!(output.status.success());
+}
+
+#[test]
+fn transcripts() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/dap");
+    let mut transcripts: Vec<_> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "dap"))
+        .collect();
+    transcripts.sort();
+    assert!(!transcripts.is_empty(), "no .dap files found in tests/dap");
+
+    for transcript in transcripts {
+        replay(&transcript);
+    }
+}
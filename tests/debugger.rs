@@ -0,0 +1,68 @@
+// Drives `lox debug` through stdin like a user would, and checks the whole session.
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+fn debug(script: &str, commands: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tree-walk-interpreter"))
+        .args(["debug", script])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(commands.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// Lines holding nothing but a literal stop at breakpoints and steps like any other.
+#[test]
+fn breakpoints_and_steps_on_literal_lines() {
+    let commands =
+        "break 3\nbreak 7\ncontinue\nnext\ncontinue\nprint name\nbacktrace\nstep\ncontinue\n";
+    let expected = "\
+Paused at line 2 in <script>.
+   2 | fun greet(name) {
+(debug) Breakpoint at line 3.
+(debug) Breakpoint at line 7.
+(debug) start
+Breakpoint at line 7 in <script>.
+   7 | 1;
+(debug) Paused at line 8 in <script>.
+   8 | print greet(\"x\"); // expect: x
+(debug) Breakpoint at line 3 in greet.
+   3 |   \"literal\";
+(debug) \"x\"
+(debug) > #0 at greet (tests/debug/literals.lox:3)
+  #1 at <script> (tests/debug/literals.lox:8)
+(debug) Paused at line 4 in greet.
+   4 |   return name;
+(debug) x
+end
+The script finished.
+";
+    assert_eq!(debug("tests/debug/literals.lox", commands), expected);
+}
+
+// Without more commands the script runs to its end.
+#[test]
+fn end_of_input_continues() {
+    let expected = "\
+Paused at line 2 in <script>.
+   2 | fun greet(name) {
+(debug) 
+start
+x
+end
+The script finished.
+";
+    assert_eq!(debug("tests/debug/literals.lox", ""), expected);
+}
@@ -0,0 +1,115 @@
+// What a host sees when running Lox: the values `eval` returns, what scripts print and how
+// every kind of error maps to an exit code.
+
+use std::io::{self, Write};
+
+use tree_walk_interpreter::{
+    capabilities::Capabilities, lox, CapturedOutput, Lox, LoxError, Value,
+};
+
+fn eval(lox: &mut Lox, source: &str) -> String {
+    lox.eval(source).unwrap().to_string()
+}
+
+#[test]
+fn eval_returns_the_last_expression() {
+    let mut lox = Lox::new();
+    assert_eq!(eval(&mut lox, "1 + 2;"), "3");
+    assert_eq!(eval(&mut lox, "\"a\" + \"b\";"), "ab");
+    assert_eq!(eval(&mut lox, "[1, nil, true];"), "[1, nil, true]");
+    // Statements other than expressions give nil.
+    assert_eq!(eval(&mut lox, "var x = 1;"), "nil");
+    assert_eq!(eval(&mut lox, "1; var y = 2;"), "nil");
+    assert_eq!(eval(&mut lox, ""), "nil");
+}
+
+#[test]
+fn globals_persist_between_evals() {
+    let mut lox = Lox::new();
+    lox.eval("var count = 1; fun bump() { count = count + 1; }")
+        .unwrap();
+    lox.eval("bump(); bump();").unwrap();
+    assert_eq!(eval(&mut lox, "count;"), "3");
+    assert!(matches!(lox.get_global("count"), Some(Value::Number(n)) if n == 3.0));
+    assert!(lox.get_global("missing").is_none());
+}
+
+#[test]
+fn natives_defined_by_the_host() {
+    let mut lox = Lox::new();
+    lox.define_native("double", 1, |args| match &args[0] {
+        Value::Number(n) => Ok(Value::Number(n * 2.0)),
+        _ => Err("Expected a number.".to_string()),
+    });
+    assert_eq!(eval(&mut lox, "double(21);"), "42");
+    assert_eq!(
+        eval(
+            &mut lox,
+            "var message; try { double(\"x\"); } catch (e) { message = e.message; } message;"
+        ),
+        "Expected a number."
+    );
+}
+
+#[test]
+fn captured_output() {
+    let output = CapturedOutput::new();
+    let mut lox = Lox::builder().output(output.clone()).build();
+    lox.eval("print 1 + 2; print \"two\";").unwrap();
+    assert_eq!(output.contents(), "3\ntwo\n");
+    assert_eq!(output.take(), "3\ntwo\n");
+    assert_eq!(output.contents(), "");
+
+    // What was printed before an error is kept.
+    assert!(lox.eval("print \"before\"; nil + 1;").is_err());
+    assert_eq!(output.take(), "before\n");
+}
+
+// Accepts everything but fails to flush, like a closed pipe.
+struct FailingFlush;
+
+impl Write for FailingFlush {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+    }
+}
+
+#[test]
+fn exit_codes() {
+    let mut script = Lox::builder()
+        .capabilities(Capabilities::none().allow_exit())
+        .build();
+
+    let compile = script.eval("print;").err().unwrap();
+    assert!(matches!(compile, LoxError::Compile(_)));
+    assert_eq!(compile.exit_code(), 65);
+
+    let runtime = script.eval("nil + 1;").err().unwrap();
+    assert!(matches!(runtime, LoxError::Runtime(_)));
+    assert_eq!(runtime.exit_code(), 70);
+
+    let exit = script.eval("os.exit(3);").err().unwrap();
+    assert!(matches!(exit, LoxError::Exit(3)));
+    assert_eq!(exit.exit_code(), 3);
+
+    let no_input = script.run_file("tests/lox/no_such_file.lox").unwrap_err();
+    assert!(matches!(no_input, LoxError::NoInput(..)));
+    assert_eq!(no_input.exit_code(), 66);
+
+    let io = Lox::builder()
+        .output(FailingFlush)
+        .build()
+        .eval("print 1;")
+        .err()
+        .unwrap();
+    assert!(matches!(io, LoxError::Io(_)));
+    assert_eq!(io.exit_code(), 74);
+
+    let usage = lox(["lox", "-e"].map(String::from).into_iter()).unwrap_err();
+    assert!(matches!(usage, LoxError::Usage(_)));
+    assert_eq!(usage.exit_code(), 64);
+}
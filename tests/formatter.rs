@@ -0,0 +1,157 @@
+// The layout the formatter produces, and that over every script under tests/ which parses
+// formatting twice changes nothing and only the layout changes, the tokens and comments stay
+// the same and in order.
+
+use std::{fs, path::Path, process::Command};
+
+use tree_walk_interpreter::{custom_scanner::Scanner, formatter::format};
+
+fn lox_files(dir: &Path, files: &mut Vec<String>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            lox_files(&path, files);
+        } else if path.extension().is_some_and(|extension| extension == "lox") {
+            files.push(path.display().to_string());
+        }
+    }
+}
+
+// The tokens as "Type lexeme" strings, comments included.
+fn tokens(source: &str) -> Vec<String> {
+    Scanner::with_comments(source)
+        .scan_tokens()
+        .iter()
+        .map(|token| token.to_string())
+        .collect()
+}
+
+#[test]
+fn formatting_is_idempotent_and_keeps_the_code() {
+    let mut files = Vec::new();
+    lox_files(Path::new("tests"), &mut files);
+    files.sort();
+
+    let mut formatted_files = 0;
+    for file in files {
+        let source = fs::read_to_string(&file).unwrap();
+        // Scripts testing syntax errors are left alone by the formatter.
+        let Ok(formatted) = format(&source) else {
+            continue;
+        };
+        formatted_files += 1;
+
+        let again = format(&formatted)
+            .unwrap_or_else(|errors| panic!("{file} doesn't parse once formatted: {}", errors[0]));
+        assert_eq!(formatted, again, "{file} changes when formatted twice");
+        assert_eq!(tokens(&source), tokens(&formatted), "{file} changes tokens");
+    }
+    assert!(formatted_files > 0, "no script formatted");
+}
+
+fn formatted(source: &str) -> String {
+    format(source).unwrap_or_else(|errors| panic!("doesn't parse: {}", errors[0]))
+}
+
+#[test]
+fn indentation_and_braces() {
+    let source = "fun f(a){if(a){while(a){a=a-1;}}else{print a;}}\nclass A<B{init(){this.x=1;}}\n";
+    assert_eq!(
+        formatted(source),
+        "\
+fun f(a) {
+  if (a) {
+    while (a) {
+      a = a - 1;
+    }
+  } else {
+    print a;
+  }
+}
+class A < B {
+  init() {
+    this.x = 1;
+  }
+}
+"
+    );
+}
+
+#[test]
+fn operator_and_comma_spacing() {
+    let source = "var x=-1+2*(3-4)/5;\nprint x>=1and!nil or x!=2;\nprint f(1,2)[0];\nvar l=[1,2,{\"a\":1}];\n";
+    assert_eq!(
+        formatted(source),
+        "\
+var x = -1 + 2 * (3 - 4) / 5;
+print x >= 1 and !nil or x != 2;
+print f(1, 2)[0];
+var l = [1, 2, {\"a\": 1}];
+"
+    );
+}
+
+#[test]
+fn comments_are_kept() {
+    let source =
+        "// top\nvar a = 1; // trailing\n\n\n/* block\n comment */\nprint a;\n{\n// inside\n}\n";
+    assert_eq!(
+        formatted(source),
+        "\
+// top
+var a = 1; // trailing
+
+/* block
+ comment */
+print a;
+{
+  // inside
+}
+"
+    );
+}
+
+#[test]
+fn long_calls_are_wrapped_at_100_columns() {
+    // Exactly 100 columns.
+    let fits = format!("print f({}, {});\n", "a".repeat(45), "b".repeat(43));
+    assert_eq!(formatted(&fits), fits);
+
+    let long = format!("print f({}, {});\n", "a".repeat(45), "b".repeat(44));
+    assert_eq!(
+        formatted(&long),
+        format!(
+            "print f(\n  {},\n  {}\n);\n",
+            "a".repeat(45),
+            "b".repeat(44)
+        )
+    );
+}
+
+#[test]
+fn check_lists_unformatted_scripts() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let unformatted = dir.join("fmt_unformatted.lox");
+    let tidy = dir.join("fmt_formatted.lox");
+    fs::write(&unformatted, "print   1;\n").unwrap();
+    fs::write(&tidy, "print 1;\n").unwrap();
+
+    let check = |path: &Path| {
+        Command::new(env!("CARGO_BIN_EXE_tree-walk-interpreter"))
+            .args(["fmt", "--check"])
+            .arg(path)
+            .output()
+            .unwrap()
+    };
+
+    let output = check(&unformatted);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("fmt_unformatted.lox"), "{stderr}");
+    // Checking leaves the file alone.
+    assert_eq!(fs::read_to_string(&unformatted).unwrap(), "print   1;\n");
+
+    let output = check(&tidy);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+}
@@ -0,0 +1,191 @@
+// Runs every .lox file under tests/ through the interpreter binary and checks it against the
+// expectations written in its comments, in the format of the Crafting Interpreters test suite:
+//
+//     print 1 + 2; // expect: 3
+//     var a = ;    // Error at ';': Expect expression.
+//     // [line 3] Error at end: Expect '}' after block.
+//     nil + 1;     // expect runtime error: Operands must be two numbers or two strings.
+//     // expect trace: at <script> (tests/lox/example.lox:4)
+//
+// A runtime error's stack trace is only checked when the script has `expect trace` lines.
+// Scripts with compile errors must exit with 65, runtime errors with 70, and everything
+// else with 0. Lines marked `[c line N]` belong to clox and are ignored.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const EXPECT: &str = "// expect: ";
+const EXPECT_RUNTIME_ERROR: &str = "// expect runtime error: ";
+const EXPECT_TRACE: &str = "// expect trace: ";
+
+#[derive(Default)]
+struct Expectations {
+    output: Vec<String>,
+    compile_errors: Vec<String>,
+    runtime_error: Option<(String, usize)>,
+    trace: Vec<String>,
+}
+
+impl Expectations {
+    fn parse(source: &str) -> Self {
+        let mut expectations = Self::default();
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+
+            if let Some((_, output)) = line.split_once(EXPECT) {
+                expectations.output.push(output.to_string());
+            } else if let Some((_, message)) = line.split_once(EXPECT_RUNTIME_ERROR) {
+                expectations.runtime_error = Some((message.to_string(), line_number));
+            } else if let Some((_, frame)) = line.split_once(EXPECT_TRACE) {
+                expectations.trace.push(frame.to_string());
+            } else if let Some(error) = compile_error(line, line_number) {
+                expectations.compile_errors.extend(error);
+            }
+        }
+        expectations
+    }
+
+    fn exit_code(&self) -> i32 {
+        if !self.compile_errors.is_empty() {
+            65
+        } else if self.runtime_error.is_some() {
+            70
+        } else {
+            0
+        }
+    }
+}
+
+// `// Error ...` is reported on the comment's own line, `// [line N] Error ...` on line N.
+// Returns Some(None) for errors which only the C implementation reports.
+fn compile_error(line: &str, line_number: usize) -> Option<Option<String>> {
+    let (_, comment) = line.split_once("// ")?;
+
+    if comment.starts_with("Error") {
+        return Some(Some(format!("[line {line_number}] {comment}")));
+    }
+
+    let comment = comment.strip_prefix('[')?;
+    let (location, error) = comment.split_once("] ")?;
+    if !error.starts_with("Error") {
+        return None;
+    }
+
+    let line_number = match location.split_once(' ') {
+        Some(("c", rest)) => return rest.starts_with("line ").then_some(None),
+        Some(("java", rest)) => rest.strip_prefix("line ")?,
+        _ => location.strip_prefix("line ")?,
+    };
+    Some(Some(format!("[line {line_number}] {error}")))
+}
+
+fn lox_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("tests directory is readable")
+        .map(|entry| entry.expect("directory entry is readable").path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            lox_files(&path, files);
+        } else if path.extension().is_some_and(|extension| extension == "lox") {
+            files.push(path);
+        }
+    }
+}
+
+// Returns a description of every mismatch, empty if the script behaved as expected.
+fn check(path: &Path) -> Vec<String> {
+    let source = fs::read_to_string(path).expect("test file is readable");
+    let expectations = Expectations::parse(&source);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tree-walk-interpreter"))
+        .arg(path)
+        .output()
+        .expect("interpreter binary runs");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout: Vec<&str> = stdout.lines().collect();
+    let stderr: Vec<&str> = stderr.lines().collect();
+
+    let mut failures = Vec::new();
+
+    if stdout != expectations.output {
+        failures.push(format!(
+            "expected output {:?}, got {:?}",
+            expectations.output, stdout
+        ));
+    }
+
+    if !expectations.compile_errors.is_empty() {
+        if stderr != expectations.compile_errors {
+            failures.push(format!(
+                "expected compile errors {:?}, got {:?}",
+                expectations.compile_errors, stderr
+            ));
+        }
+    } else if let Some((message, line)) = &expectations.runtime_error {
+        let expected = [message.clone(), format!("[line {line}]")];
+        if stderr.len() < 2 || stderr[..2] != expected {
+            failures.push(format!(
+                "expected runtime error {expected:?}, got {stderr:?}"
+            ));
+        }
+        let trace: Vec<&str> = stderr.iter().skip(2).map(|line| line.trim()).collect();
+        if !expectations.trace.is_empty() && trace != expectations.trace {
+            failures.push(format!(
+                "expected stack trace {:?}, got {trace:?}",
+                expectations.trace
+            ));
+        }
+    } else if !stderr.is_empty() {
+        failures.push(format!("expected no errors, got {stderr:?}"));
+    }
+
+    let code = output.status.code();
+    if code != Some(expectations.exit_code()) {
+        failures.push(format!(
+            "expected exit code {}, got {code:?}",
+            expectations.exit_code()
+        ));
+    }
+
+    failures
+}
+
+#[test]
+fn golden_files() {
+    let mut files = Vec::new();
+    lox_files(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .as_path(),
+        &mut files,
+    );
+    assert!(!files.is_empty(), "no .lox files found under tests/");
+
+    let mut failed = Vec::new();
+    for path in &files {
+        let failures = check(path);
+        if !failures.is_empty() {
+            failed.push(format!(
+                "{}:\n    {}",
+                path.display(),
+                failures.join("\n    ")
+            ));
+        }
+    }
+
+    assert!(
+        failed.is_empty(),
+        "{} of {} golden files failed:\n{}",
+        failed.len(),
+        files.len(),
+        failed.join("\n")
+    );
+}
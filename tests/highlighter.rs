@@ -0,0 +1,133 @@
+// Highlighting cuts the source into styled pieces which put back together give the source,
+// whatever it contains.
+
+use tree_walk_interpreter::highlighter::{Highlighter, Style};
+
+fn pieces(source: &str) -> Vec<(Style, &str)> {
+    let pieces = Highlighter::new().highlight(source);
+    let joined: String = pieces.iter().map(|(_, text)| *text).collect();
+    assert_eq!(joined, source);
+    pieces
+}
+
+// The pieces which aren't plain.
+fn styled(source: &str) -> Vec<(Style, &str)> {
+    pieces(source)
+        .into_iter()
+        .filter(|(style, _)| *style != Style::Plain)
+        .collect()
+}
+
+#[test]
+fn names_by_what_they_refer_to() {
+    let source = "fun f(a) { var b = a; return b.c + clock(); }\nclass K {}\nvar g = f(1);\n";
+    assert_eq!(
+        styled(source),
+        [
+            (Style::Keyword, "fun"),
+            (Style::Function, "f"),
+            (Style::Parameter, "a"),
+            (Style::Keyword, "var"),
+            (Style::Local, "b"),
+            (Style::Operator, "="),
+            (Style::Parameter, "a"),
+            (Style::Keyword, "return"),
+            (Style::Local, "b"),
+            (Style::Field, "c"),
+            (Style::Operator, "+"),
+            (Style::Builtin, "clock"),
+            (Style::Keyword, "class"),
+            (Style::Class, "K"),
+            (Style::Keyword, "var"),
+            (Style::Global, "g"),
+            (Style::Operator, "="),
+            (Style::Function, "f"),
+            (Style::Number, "1"),
+        ]
+    );
+}
+
+#[test]
+fn html_is_escaped() {
+    let html = Highlighter::new().html("print \"<b>&'\" < 1; // a > b\n");
+    assert_eq!(
+        html,
+        "<pre class=\"lox\"><code><span class=\"lox-keyword\">print</span> \
+         <span class=\"lox-string\">&quot;&lt;b&gt;&amp;'&quot;</span> \
+         <span class=\"lox-operator\">&lt;</span> <span class=\"lox-number\">1</span>; \
+         <span class=\"lox-comment\">// a &gt; b</span>\n</code></pre>\n"
+    );
+}
+
+#[test]
+fn ansi_colors() {
+    let ansi = Highlighter::new().ansi("var x = nil;");
+    assert_eq!(
+        ansi,
+        "\x1b[35mvar\x1b[0m \x1b[34mx\x1b[0m = \x1b[33mnil\x1b[0m;"
+    );
+}
+
+#[test]
+fn multiline_strings() {
+    let source = "var s = \"one\ntwo\"; print s;\n";
+    assert_eq!(
+        styled(source),
+        [
+            (Style::Keyword, "var"),
+            (Style::Global, "s"),
+            (Style::Operator, "="),
+            (Style::String, "\"one\ntwo\""),
+            (Style::Keyword, "print"),
+            (Style::Global, "s"),
+        ]
+    );
+    // Left open to the end.
+    assert_eq!(
+        pieces("print \"one\ntwo"),
+        [(Style::Keyword, "print"), (Style::Plain, " \"one\ntwo")]
+    );
+}
+
+// Columns count characters, so names after multi-byte text are still found.
+#[test]
+fn unicode_before_names() {
+    let source = "var s = \"héllo\"; var t = \"ü\" + s; // ñ\nprint t;\n";
+    assert_eq!(
+        styled(source),
+        [
+            (Style::Keyword, "var"),
+            (Style::Global, "s"),
+            (Style::Operator, "="),
+            (Style::String, "\"héllo\""),
+            (Style::Keyword, "var"),
+            (Style::Global, "t"),
+            (Style::Operator, "="),
+            (Style::String, "\"ü\""),
+            (Style::Operator, "+"),
+            (Style::Global, "s"),
+            (Style::Comment, "// ñ"),
+            (Style::Keyword, "print"),
+            (Style::Global, "t"),
+        ]
+    );
+}
+
+// Identifiers are ASCII, the scanner rejects other letters, which are left plain along with
+// the names as the source doesn't parse.
+#[test]
+fn unicode_identifiers() {
+    assert_eq!(
+        pieces("var café = 1;"),
+        [
+            (Style::Keyword, "var"),
+            (Style::Plain, " "),
+            (Style::Plain, "caf"),
+            (Style::Plain, "é "),
+            (Style::Operator, "="),
+            (Style::Plain, " "),
+            (Style::Number, "1"),
+            (Style::Plain, ";"),
+        ]
+    );
+}
@@ -0,0 +1,117 @@
+// The limits end a run with a runtime error which `catch` can't stop, and the interpreter
+// can run again afterwards.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use tree_walk_interpreter::{capabilities::Capabilities, limits::Limits, Lox, LoxError};
+
+fn error_message(lox: &mut Lox, source: &str) -> String {
+    match lox.eval(source) {
+        Err(LoxError::Runtime(error)) => error.message().to_string(),
+        Err(error) => panic!("expected a runtime error, got {error}"),
+        Ok(value) => panic!("expected a runtime error, got {value}"),
+    }
+}
+
+fn with(limits: Limits) -> Lox {
+    Lox::builder().limits(limits).build()
+}
+
+#[test]
+fn fuel() {
+    let mut lox = with(Limits::new().fuel(1000));
+    assert_eq!(
+        error_message(&mut lox, "while (true) {}"),
+        "Execution ran out of fuel."
+    );
+    assert_eq!(
+        error_message(&mut lox, "try { while (true) {} } catch (e) {}"),
+        "Execution ran out of fuel."
+    );
+    // Every run starts with a full tank.
+    assert_eq!(lox.eval("1 + 1;").unwrap().to_string(), "2");
+}
+
+#[test]
+fn timeout() {
+    let mut lox = with(Limits::new().timeout(Duration::from_millis(50)));
+    assert_eq!(
+        error_message(&mut lox, "try { while (true) {} } catch (e) {}"),
+        "Execution timed out."
+    );
+}
+
+#[test]
+fn sleep_stops_at_the_timeout() {
+    let mut lox = Lox::builder()
+        .capabilities(Capabilities::none().allow_clock())
+        .limits(Limits::new().timeout(Duration::from_millis(50)))
+        .build();
+    let start = Instant::now();
+    assert_eq!(
+        error_message(&mut lox, "try { time.sleep(60); } catch (e) {}"),
+        "Execution timed out."
+    );
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn heap() {
+    let mut lox = with(Limits::new().max_heap(1000));
+    assert_eq!(
+        error_message(
+            &mut lox,
+            "var s = \"x\"; try { while (true) s = s + s; } catch (e) {}"
+        ),
+        "Memory limit exceeded."
+    );
+    assert_eq!(
+        error_message(&mut lox, "var l = []; while (true) l.push(l.len());"),
+        "Memory limit exceeded."
+    );
+}
+
+#[test]
+fn call_depth() {
+    let mut lox = with(Limits::new().max_call_depth(10));
+    assert_eq!(
+        lox.eval("fun count(n) { if (n == 0) return 0; return 1 + count(n - 1); } count(9);")
+            .unwrap()
+            .to_string(),
+        "9"
+    );
+    assert_eq!(
+        error_message(&mut lox, "try { count(10); } catch (e) {}"),
+        "Stack overflow."
+    );
+    assert_eq!(lox.eval("count(9);").unwrap().to_string(), "9");
+}
+
+// Threads get 2 MiB of stack unless asked for more, the default limits must fit in that.
+#[test]
+fn runaway_recursion_on_a_default_thread() {
+    let message = thread::spawn(|| {
+        let mut lox = Lox::new();
+        let message = error_message(&mut lox, "fun f(n) { return f(n + 1); } f(0);");
+        let fib = lox
+            .eval("fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); } fib(15);")
+            .unwrap()
+            .to_string();
+        (message, fib)
+    })
+    .join()
+    .unwrap();
+    assert_eq!(message, ("Stack overflow.".to_string(), "610".to_string()));
+}
+
+#[test]
+fn stack_limit() {
+    let mut lox = with(Limits::new().max_stack(64 * 1024));
+    assert_eq!(
+        error_message(&mut lox, "fun f(n) { return f(n + 1); } f(0);"),
+        "Stack overflow."
+    );
+}
@@ -0,0 +1,151 @@
+// Each lint rule on a small example, given as (line, column, rule) triples.
+
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use tree_walk_interpreter::linter::Linter;
+
+fn lint(source: &str) -> Vec<(u32, u32, &'static str)> {
+    lint_with(Linter::new(), source)
+}
+
+fn lint_with(linter: Linter, source: &str) -> Vec<(u32, u32, &'static str)> {
+    linter
+        .lint(source)
+        .unwrap_or_else(|errors| panic!("{}", errors[0]))
+        .into_iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.span.line,
+                diagnostic.span.column,
+                diagnostic.rule,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn unused_variables_and_parameters() {
+    let source =
+        "fun f(a, b, _c) {\n  var x = 1;\n  var _y = 2;\n  return a;\n}\nvar global = 1;\n";
+    assert_eq!(
+        lint(source),
+        [(1, 10, "unused-parameter"), (2, 7, "unused-variable")]
+    );
+}
+
+#[test]
+fn unreachable_code() {
+    let source = "fun f() {\n  return;\n  print 1;\n  print 2;\n}\nwhile (true) {\n  break;\n  print \"x\";\n}\n";
+    let diagnostics = Linter::new().lint(source).unwrap();
+    let spans: Vec<_> = diagnostics.iter().map(|d| (d.rule, d.span)).collect();
+    assert_eq!(spans.len(), 2, "one per dead stretch");
+    assert_eq!(spans[0].0, "unreachable-code");
+    assert_eq!(
+        (spans[0].1.line, spans[0].1.column, spans[0].1.end_column),
+        (3, 3, 11)
+    );
+    assert_eq!((spans[1].1.line, spans[1].1.column), (8, 3));
+}
+
+// Statements made only of literals still have a line, even when they share it.
+#[test]
+fn unreachable_code_on_one_line() {
+    assert_eq!(
+        lint("fun f() { return; print \"x\"; }"),
+        [(1, 1, "unreachable-code")]
+    );
+    assert_eq!(
+        lint("fun f() { throw 1; 2; }"),
+        [(1, 1, "unreachable-code")]
+    );
+}
+
+#[test]
+fn shadowed_variable() {
+    let source = "var a = 1;\n{\n  var a = 2;\n  print a;\n}\n";
+    assert_eq!(lint(source), [(3, 7, "shadowed-variable")]);
+}
+
+#[test]
+fn assignment_in_condition() {
+    let source = "var a = 1;\nif (a = 2) print a;\nwhile ((a = 3)) print a;\n";
+    assert_eq!(
+        lint(source),
+        [
+            (2, 5, "assignment-in-condition"),
+            (3, 9, "assignment-in-condition")
+        ]
+    );
+}
+
+#[test]
+fn self_comparison_and_assignment() {
+    let source =
+        "var a = 1;\nprint a == a;\nprint a == a + 0;\na = a;\nvar l = [1];\nl[0] = l[0];\n";
+    assert_eq!(
+        lint(source),
+        [
+            (2, 9, "self-comparison"),
+            (4, 1, "self-assignment"),
+            (6, 2, "self-assignment")
+        ]
+    );
+}
+
+#[test]
+fn empty_blocks() {
+    let source = "if (true) {}\nwhile (false) {}\ntry {} catch (e) {}\n";
+    assert_eq!(
+        lint(source),
+        [
+            (1, 1, "empty-block"),
+            (2, 1, "empty-block"),
+            (3, 1, "empty-block"),
+            (3, 1, "empty-block")
+        ]
+    );
+
+    // Loops with an increment are desugared with the body in a block of its own.
+    let source = "for (var i = 0; i < 3; i = i + 1) {}\nfor (;false;) {}\n";
+    assert_eq!(lint(source), [(1, 1, "empty-block"), (2, 1, "empty-block")]);
+}
+
+#[test]
+fn allowed_and_ignored_rules() {
+    let source = "var a = 1;\n{\n  var a = 2;\n  print a;\n}\n";
+    assert_eq!(
+        lint_with(Linter::new().allow("shadowed-variable"), source),
+        []
+    );
+
+    let source = "var a = 1;\n{\n  // lox-ignore: shadowed-variable\n  var a = 2;\n  var b = a; // lox-ignore: unused-variable\n}\n";
+    assert_eq!(lint(source), []);
+}
+
+#[test]
+fn code_which_does_not_compile() {
+    assert!(Linter::new().lint("var = 1;").is_err());
+}
+
+#[test]
+fn json_output_into_a_closed_pipe() {
+    // More warnings than a pipe buffers, so writing them fails once the reader is gone.
+    let script = Path::new(env!("CARGO_TARGET_TMPDIR")).join("lint_many_warnings.lox");
+    fs::write(&script, "if (true) {}\n".repeat(5000)).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tree-walk-interpreter"))
+        .args(["lint", "--json"])
+        .arg(&script)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
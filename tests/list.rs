@@ -0,0 +1,46 @@
+// Index errors point at the whole subscript, for tools which show where an error happened.
+
+use tree_walk_interpreter::{token::Span, Lox, LoxError};
+
+fn index_error(source: &str) -> (String, Span) {
+    match Lox::new().eval(source) {
+        Err(LoxError::Runtime(error)) => (error.message().to_string(), error.span()),
+        Err(error) => panic!("expected a runtime error, got {error}"),
+        Ok(value) => panic!("expected a runtime error, got {value}"),
+    }
+}
+
+#[test]
+fn negative_index() {
+    let (message, span) = index_error("var xs = [1, 2];\nxs[-1];");
+    assert_eq!(message, "List index can't be negative, got -1.");
+    assert_eq!(
+        span,
+        Span {
+            line: 2,
+            column: 3,
+            end_column: 7
+        }
+    );
+}
+
+#[test]
+fn assignment_out_of_bounds() {
+    let (message, span) = index_error("var xs = [];\nxs[ 0 ] = 1;");
+    assert_eq!(message, "List index 0 out of bounds for length 0.");
+    assert_eq!(
+        span,
+        Span {
+            line: 2,
+            column: 3,
+            end_column: 8
+        }
+    );
+}
+
+#[test]
+fn not_a_list() {
+    let (message, span) = index_error("var n = 1; n[0];");
+    assert_eq!(message, "Only lists and maps can be indexed.");
+    assert_eq!((span.column, span.end_column), (13, 16));
+}
@@ -0,0 +1,368 @@
+// Drives `lox lsp` the way an editor would, writing JSON-RPC messages to its stdin and
+// reading the replies from its stdout.
+
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use serde_json::{json, Value};
+
+const URI: &str = "file:///greet.lox";
+
+const SOURCE: &str = "\
+var greeting = \"hi\";
+fun greet(name) {
+  print greeting + name;
+}
+class Dog {
+  bark() {
+    return greet(\"woof\");
+  }
+}
+greet(\"x\");
+Dog().bark();
+";
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+    // Notifications which arrived while waiting for a response.
+    notifications: VecDeque<Value>,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_tree-walk-interpreter"))
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("the interpreter should start");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        let mut client = Client {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+            notifications: VecDeque::new(),
+        };
+        let result = client.request("initialize", json!({ "capabilities": {} }));
+        assert_eq!(result["result"]["capabilities"]["renameProvider"], true);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    // Returns the whole response, so errors can be checked too.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let message = self.receive();
+            if message["id"] == id {
+                return message;
+            }
+            self.notifications.push_back(message);
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn diagnostics(&mut self) -> Vec<Value> {
+        let message = match self.notifications.pop_front() {
+            Some(message) => message,
+            None => self.receive(),
+        };
+        assert_eq!(message["method"], "textDocument/publishDiagnostics");
+        message["params"]["diagnostics"].as_array().unwrap().clone()
+    }
+
+    fn open(&mut self, text: &str) -> Vec<Value> {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "lox", "version": 1, "text": text },
+            }),
+        );
+        self.diagnostics()
+    }
+
+    fn change(&mut self, text: &str) -> Vec<Value> {
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{ "text": text }],
+            }),
+        );
+        self.diagnostics()
+    }
+
+    fn at(&mut self, method: &str, line: u32, character: u32, extra: Value) -> Value {
+        let mut params = json!({
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+        });
+        if let (Some(params), Some(extra)) = (params.as_object_mut(), extra.as_object()) {
+            params.extend(extra.clone());
+        }
+        self.request(method, params)
+    }
+
+    fn exit(mut self) -> Option<i32> {
+        self.notify("exit", json!(null));
+        self.child.wait().unwrap().code()
+    }
+}
+
+fn range(line: u32, start: u32, end: u32) -> Value {
+    json!({
+        "start": { "line": line, "character": start },
+        "end": { "line": line, "character": end },
+    })
+}
+
+fn labels(completion: &Value) -> Vec<String> {
+    completion["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn diagnostics() {
+    let mut client = Client::start();
+
+    let errors = client.open("var a = ;\nprint a;\n");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["severity"], 1);
+    assert_eq!(errors[0]["range"], range(0, 0, 9));
+    assert_eq!(errors[0]["message"], "Error at ';': Expect expression.");
+
+    let warnings = client.change("{\n  var unused = 1;\n}\n");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["severity"], 2);
+    assert_eq!(warnings[0]["code"], "unused-variable");
+    assert_eq!(warnings[0]["range"], range(1, 6, 12));
+
+    // Statements made only of literals, sharing a line with the return.
+    let warnings = client.change("fun f() { return; print \"x\"; }\n");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["code"], "unreachable-code");
+    assert_eq!(warnings[0]["range"], range(0, 0, 30));
+
+    assert!(client.change(SOURCE).is_empty());
+
+    client.notify(
+        "textDocument/didClose",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    assert!(client.diagnostics().is_empty());
+
+    client.request("shutdown", json!(null));
+    assert_eq!(client.exit(), Some(0));
+}
+
+#[test]
+fn navigation() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    // From the call of `greet` on the last line but one.
+    let definition = client.at("textDocument/definition", 9, 2, json!({}));
+    assert_eq!(definition["result"]["uri"], URI);
+    assert_eq!(definition["result"]["range"], range(1, 4, 9));
+
+    let references = client.at(
+        "textDocument/references",
+        1,
+        5,
+        json!({ "context": { "includeDeclaration": true } }),
+    );
+    let ranges: Vec<&Value> = references["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| &location["range"])
+        .collect();
+    assert_eq!(
+        ranges,
+        [&range(1, 4, 9), &range(6, 11, 16), &range(9, 0, 5)]
+    );
+
+    // Methods are found through the properties with their name.
+    let references = client.at(
+        "textDocument/references",
+        5,
+        3,
+        json!({ "context": { "includeDeclaration": false } }),
+    );
+    assert_eq!(references["result"][0]["range"], range(10, 6, 10));
+
+    let nothing = client.at("textDocument/definition", 2, 1, json!({}));
+    assert_eq!(nothing["result"], Value::Null);
+
+    client.request("shutdown", json!(null));
+    assert_eq!(client.exit(), Some(0));
+}
+
+#[test]
+fn hover_and_symbols() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    let hover = client.at("textDocument/hover", 2, 10, json!({}));
+    assert_eq!(
+        hover["result"]["contents"]["value"],
+        "```lox\nvar greeting\n```\nglobal variable"
+    );
+    let hover = client.at("textDocument/hover", 2, 20, json!({}));
+    assert_eq!(
+        hover["result"]["contents"]["value"],
+        "```lox\nname\n```\nparameter of greet"
+    );
+    let hover = client.at("textDocument/hover", 5, 3, json!({}));
+    assert_eq!(
+        hover["result"]["contents"]["value"],
+        "```lox\nfun Dog.bark()\n```\nmethod of class Dog"
+    );
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let symbols = symbols["result"].as_array().unwrap();
+    assert_eq!(symbols.len(), 2);
+    assert_eq!(symbols[0]["name"], "greet");
+    assert_eq!(symbols[0]["kind"], 12);
+    assert_eq!(symbols[1]["name"], "Dog");
+    assert_eq!(symbols[1]["kind"], 5);
+    assert_eq!(symbols[1]["children"][0]["name"], "bark");
+    assert_eq!(symbols[1]["children"][0]["range"], range(5, 2, 6));
+
+    client.request("shutdown", json!(null));
+    assert_eq!(client.exit(), Some(0));
+}
+
+#[test]
+fn completion() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    let inside = labels(&client.at("textDocument/completion", 2, 2, json!({})));
+    for name in ["name", "greeting", "greet", "Dog", "print", "clock"] {
+        assert!(inside.contains(&name.to_string()), "{name} in {inside:?}");
+    }
+    let outside = labels(&client.at("textDocument/completion", 9, 0, json!({})));
+    assert!(!outside.contains(&String::from("name")));
+
+    // While typing, the text doesn't parse and the last analysis is used.
+    client.change(&format!("{SOURCE}Dog()."));
+    let after_dot = labels(&client.at("textDocument/completion", 11, 6, json!({})));
+    assert_eq!(after_dot, ["bark"]);
+
+    client.request("shutdown", json!(null));
+    assert_eq!(client.exit(), Some(0));
+}
+
+#[test]
+fn rename() {
+    let mut client = Client::start();
+    client.open(SOURCE);
+
+    let rename = client.at("textDocument/rename", 9, 0, json!({ "newName": "hello" }));
+    let edits = rename["result"]["changes"][URI].as_array().unwrap();
+    assert_eq!(edits.len(), 3);
+    assert!(edits.iter().all(|edit| edit["newText"] == "hello"));
+    assert_eq!(edits[1]["range"], range(6, 11, 16));
+
+    for name in ["class", "9lives", ""] {
+        let rename = client.at("textDocument/rename", 9, 0, json!({ "newName": name }));
+        assert_eq!(rename["error"]["code"], -32602);
+    }
+
+    client.request("shutdown", json!(null));
+    assert_eq!(client.exit(), Some(0));
+}
+
+#[test]
+fn rename_refuses_methods() {
+    let mut client = Client::start();
+    client.open(
+        "\
+class Square {
+  area() { return 4; }
+}
+class Circle {
+  area() { return 3; }
+}
+var shape = Square();
+shape.area = 1;
+print Circle().area();
+",
+    );
+
+    // Without types, any `area` could be either method or the field.
+    for (line, character) in [(1, 2), (4, 2), (7, 6), (8, 15)] {
+        let rename = client.at(
+            "textDocument/rename",
+            line,
+            character,
+            json!({ "newName": "size" }),
+        );
+        assert_eq!(rename["error"]["code"], -32803, "at {line}:{character}");
+    }
+
+    let rename = client.at("textDocument/rename", 6, 12, json!({ "newName": "Box" }));
+    let edits = rename["result"]["changes"][URI].as_array().unwrap();
+    assert_eq!(edits.len(), 2);
+    assert_eq!(edits[0]["range"], range(0, 6, 12));
+
+    client.request("shutdown", json!(null));
+    assert_eq!(client.exit(), Some(0));
+}
+
+#[test]
+fn protocol() {
+    let mut client = Client::start();
+
+    let unknown = client.request("workspace/unknown", json!({}));
+    assert_eq!(unknown["error"]["code"], -32601);
+
+    // Exiting without being shut down first is an error.
+    assert_eq!(client.exit(), Some(1));
+}
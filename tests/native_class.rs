@@ -0,0 +1,123 @@
+// Rust structs exposed with ClassBuilder: constructors, fields, read-only fields and methods
+// all go through the struct.
+
+use tree_walk_interpreter::{
+    native_class::{instance, with_data},
+    ClassBuilder, FromLox, Lox, LoxError,
+};
+
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+fn point_class() -> ClassBuilder<Point> {
+    ClassBuilder::<Point>::new("Point")
+        .constructor(2, |args| {
+            Ok(Point {
+                x: f64::from_lox(&args[0])?,
+                y: f64::from_lox(&args[1])?,
+            })
+        })
+        .field("x", |p| p.x, |p, x| p.x = x)
+        .read_only_field("y", |p| p.y)
+        .method("length", 0, |p, _| Ok((p.x * p.x + p.y * p.y).sqrt()))
+        .method("scale", 1, |p, args| {
+            let factor = f64::from_lox(&args[0])?;
+            p.x *= factor;
+            p.y *= factor;
+            Ok(())
+        })
+        .method("add", 1, |p, args| {
+            let (x, y) = with_data(&args[0], |other: &Point| (other.x, other.y))
+                .ok_or("Expected another Point.")?;
+            p.x += x;
+            p.y += y;
+            Ok(())
+        })
+}
+
+fn lox() -> Lox {
+    let mut lox = Lox::new();
+    lox.define_class(point_class());
+    lox
+}
+
+fn eval(lox: &mut Lox, source: &str) -> String {
+    lox.eval(source).unwrap().to_string()
+}
+
+fn error_message(lox: &mut Lox, source: &str) -> String {
+    match lox.eval(source) {
+        Err(LoxError::Runtime(error)) => error.message().to_string(),
+        Err(error) => panic!("expected a runtime error, got {error}"),
+        Ok(value) => panic!("expected a runtime error, got {value}"),
+    }
+}
+
+#[test]
+fn fields_and_methods() {
+    let mut lox = lox();
+    lox.eval("var p = Point(3, 4);").unwrap();
+    assert_eq!(eval(&mut lox, "p.x;"), "3");
+    assert_eq!(eval(&mut lox, "p.y;"), "4");
+    assert_eq!(eval(&mut lox, "p.length();"), "5");
+
+    // Setters and methods write through to the struct.
+    lox.eval("p.x = 6; p.scale(0.5);").unwrap();
+    assert_eq!(eval(&mut lox, "p.x;"), "3");
+    assert_eq!(eval(&mut lox, "p.y;"), "2");
+    lox.eval("p.add(Point(1, 1));").unwrap();
+    assert_eq!(eval(&mut lox, "p.length();"), "5");
+
+    // Other properties are plain Lox fields.
+    assert_eq!(eval(&mut lox, "p.label = \"origin\"; p.label;"), "origin");
+}
+
+#[test]
+fn read_only_and_mistyped_properties() {
+    let mut lox = lox();
+    lox.eval("var p = Point(3, 4);").unwrap();
+    assert_eq!(
+        error_message(&mut lox, "p.y = 1;"),
+        "Can't assign to read-only property 'y'."
+    );
+    assert_eq!(
+        error_message(&mut lox, "p.length = 1;"),
+        "Can't assign to read-only property 'length'."
+    );
+    assert_eq!(
+        error_message(&mut lox, "p.x = \"far\";"),
+        "Expected a number, got string."
+    );
+    assert_eq!(
+        error_message(&mut lox, "Point(1, nil);"),
+        "Expected a number, got nil."
+    );
+    assert_eq!(eval(&mut lox, "p.x;"), "3");
+}
+
+// The struct is borrowed while its method runs, so the method sees itself as not a Point
+// instead of panicking.
+#[test]
+fn passing_an_instance_to_its_own_method() {
+    let mut lox = lox();
+    lox.eval("var p = Point(3, 4);").unwrap();
+    assert_eq!(
+        error_message(&mut lox, "p.add(p);"),
+        "Expected another Point."
+    );
+    assert_eq!(eval(&mut lox, "p.x;"), "3");
+}
+
+#[test]
+fn instances_created_from_rust() {
+    let mut lox = Lox::new();
+    let class = lox.define_class(point_class());
+    lox.define_global("origin", instance(&class, Point { x: 0.0, y: 0.0 }));
+    lox.eval("origin.x = 2;").unwrap();
+
+    let origin = lox.get_global("origin").unwrap();
+    assert_eq!(with_data(&origin, |p: &Point| (p.x, p.y)), Some((2.0, 0.0)));
+    assert_eq!(with_data(&origin, |s: &String| s.len()), None);
+}
@@ -0,0 +1,100 @@
+// The REPL's handling of one input, without the line editor: when input is complete, the
+// optional `;` and the `:` commands.
+
+use tree_walk_interpreter::{
+    repl::{is_incomplete, run_input},
+    Lox, LoxError,
+};
+
+fn run(lox: &mut Lox, source: &str) -> Result<String, LoxError> {
+    let mut out = Vec::new();
+    run_input(lox, source, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+fn shows(lox: &mut Lox, source: &str) -> String {
+    run(lox, source).unwrap_or_else(|error| panic!("{source}: {error}"))
+}
+
+#[test]
+fn incomplete_input() {
+    assert!(is_incomplete("fun f() {\n"));
+    assert!(is_incomplete("var l = [1,\n"));
+    assert!(is_incomplete("print (1 +\n"));
+    assert!(is_incomplete("var s = \"first line\n"));
+    assert!(is_incomplete("/* a comment\n"));
+    assert!(is_incomplete("class A {\n  m() {\n  }\n"));
+
+    assert!(!is_incomplete("fun f() {\n}\n"));
+    assert!(!is_incomplete("1 + 2\n"));
+    // Left for the parser to report.
+    assert!(!is_incomplete("print 1 +\n"));
+    assert!(!is_incomplete(")\n"));
+    // Commands are one line, whatever they contain.
+    assert!(!is_incomplete(":type [1,\n"));
+}
+
+#[test]
+fn expression_values_are_shown() {
+    let mut lox = Lox::new();
+    assert_eq!(shows(&mut lox, "1 + 2;\n"), "3\n");
+    assert_eq!(shows(&mut lox, "\"a\" + \"b\";\n"), "\"ab\"\n");
+    // Declarations and nil show nothing.
+    assert_eq!(shows(&mut lox, "var x = 1;\n"), "");
+    assert_eq!(shows(&mut lox, "nil;\n"), "");
+    assert_eq!(shows(&mut lox, "x;\n"), "1\n");
+}
+
+#[test]
+fn final_semicolon_is_optional() {
+    let mut lox = Lox::new();
+    assert_eq!(shows(&mut lox, "1 + 2\n"), "3\n");
+    assert_eq!(shows(&mut lox, "var y = 2\n"), "");
+    assert_eq!(shows(&mut lox, "y * 2\n"), "4\n");
+    // Only the last one.
+    assert!(matches!(run(&mut lox, "1 2\n"), Err(LoxError::Compile(_))));
+}
+
+#[test]
+fn commands() {
+    let mut lox = Lox::new();
+    assert!(shows(&mut lox, ":help\n").contains(":type <expr>"));
+    assert_eq!(shows(&mut lox, ":type 1 + 2\n"), "number\n");
+    assert_eq!(shows(&mut lox, ":type [1, \"a\"]\n"), "list\n");
+
+    shows(&mut lox, "var answer = 42;\n");
+    assert!(shows(&mut lox, ":env\n").contains("answer = 42\n"));
+    assert!(shows(&mut lox, ":time answer + 1\n").starts_with("43\nTook "));
+    assert_eq!(shows(&mut lox, ":ast 1 + 2\n"), "(; (+ 1 2))\n");
+    assert_eq!(
+        shows(&mut lox, ":tokens var a;\n"),
+        "   1:1   Var var\n   1:5   Identifier a\n   1:6   SemiColon ;\n   1:0   Eof \n"
+    );
+
+    shows(&mut lox, ":reset\n");
+    assert!(!shows(&mut lox, ":env\n").contains("answer"));
+
+    shows(&mut lox, ":load tests/lox/map/cycle.lox\n");
+    assert!(shows(&mut lox, ":env\n").contains("m = "));
+}
+
+#[test]
+fn command_errors() {
+    let mut lox = Lox::new();
+    let usage = |result| match result {
+        Err(LoxError::Usage(message)) => message,
+        _ => panic!("expected a usage error"),
+    };
+    assert_eq!(
+        usage(run(&mut lox, ":type\n")),
+        "Command ':type' expects an argument."
+    );
+    assert_eq!(
+        usage(run(&mut lox, ":nope\n")),
+        "Unknown command ':nope', see :help."
+    );
+    assert!(matches!(
+        run(&mut lox, ":type undefined\n"),
+        Err(LoxError::Runtime(_))
+    ));
+}